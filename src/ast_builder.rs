@@ -1,213 +1,4968 @@
+#![allow(clippy::ptr_arg)]
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 
-#[derive(Clone)]
-enum StatementType {
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum StatementType {
     Select,
     Update,
     Delete,
     Insert,
+    Truncate,
+    Merge,
+    Raw,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum QueryError {
+    MissingFields { model: String },
+    MissingWhereClause { statement: String },
+    DisallowedColumn { column: String },
+    UndefinedColumn { column: String },
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::MissingFields { model } => {
+                write!(f, "update query on `{}` must have fields", model)
+            }
+            QueryError::MissingWhereClause { statement } => write!(
+                f,
+                "`{}` has no WHERE clause; call allow_full_scan() if this is intended",
+                statement
+            ),
+            QueryError::DisallowedColumn { column } => {
+                write!(f, "column `{}` is not in the allowed list", column)
+            }
+            QueryError::UndefinedColumn { column } => {
+                write!(f, "column `{}` is not defined", column)
+            }
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Dialect {
+    Postgres,
+    MySQL,
+    Sqlite,
+}
+
+impl Dialect {
+    fn quote_identifier(&self, name: &str) -> String {
+        match self {
+            Dialect::MySQL => format!("`{}`", name.replace('`', "``")),
+            Dialect::Postgres | Dialect::Sqlite => escape_identifier(name),
+        }
+    }
+
+    // MySQL treats a backslash inside a string literal as an escape
+    // character, so it is doubled along with the quote.
+    fn escape_literal(&self, value: &str) -> String {
+        match self {
+            Dialect::MySQL => escape_literal(&value.replace('\\', "\\\\")),
+            Dialect::Postgres | Dialect::Sqlite => escape_literal(value),
+        }
+    }
+
+    fn boolean_literal(&self, value: bool) -> &'static str {
+        match (self, value) {
+            (Dialect::Sqlite, true) => "1",
+            (Dialect::Sqlite, false) => "0",
+            (_, true) => "TRUE",
+            (_, false) => "FALSE",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum OrderDirection {
+    Asc,
+    Desc,
+}
+
+impl OrderDirection {
+    fn keyword(&self) -> &'static str {
+        match self {
+            OrderDirection::Asc => "ASC",
+            OrderDirection::Desc => "DESC",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum NullsPlacement {
+    First,
+    Last,
+}
+
+impl NullsPlacement {
+    fn keyword(&self) -> &'static str {
+        match self {
+            NullsPlacement::First => "NULLS FIRST",
+            NullsPlacement::Last => "NULLS LAST",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum InsertMode {
+    Default,
+    OrReplace,
+    OrIgnore,
+}
+
+impl InsertMode {
+    fn keyword(&self) -> &'static str {
+        match self {
+            InsertMode::Default => "INSERT INTO",
+            InsertMode::OrReplace => "INSERT OR REPLACE INTO",
+            InsertMode::OrIgnore => "INSERT OR IGNORE INTO",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Right,
+    Full,
+}
+
+impl JoinKind {
+    fn keyword(&self) -> &'static str {
+        match self {
+            JoinKind::Inner => "INNER JOIN",
+            JoinKind::Left => "LEFT JOIN",
+            JoinKind::Right => "RIGHT JOIN",
+            JoinKind::Full => "FULL OUTER JOIN",
+        }
+    }
+}
+
+pub enum Condition {
+    Eq(String, String),
+    Gt(String, String),
+    Lt(String, String),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+impl Condition {
+    pub fn to_sql(&self) -> String {
+        let mut part = ParameterizedPart::new();
+        self.push_to(&mut part);
+        part.inline()
+    }
+
+    fn push_to(&self, part: &mut ParameterizedPart) {
+        match self {
+            Condition::Eq(column, value) => push_comparison(part, column, "=", value),
+            Condition::Gt(column, value) => push_comparison(part, column, ">", value),
+            Condition::Lt(column, value) => push_comparison(part, column, "<", value),
+            Condition::And(left, right) => {
+                left.push_operand(part);
+                part.push_infix("AND");
+                right.push_operand(part);
+            }
+            Condition::Or(left, right) => {
+                left.push_to(part);
+                part.push_infix("OR");
+                right.push_to(part);
+            }
+        }
+    }
+
+    // OR binds looser than AND, so an OR nested under an AND is the only
+    // combination that needs parentheses.
+    fn push_operand(&self, part: &mut ParameterizedPart) {
+        match self {
+            Condition::Or(_, _) => {
+                part.push_sql("(");
+                self.push_to(part);
+                part.push_sql(")");
+            }
+            _ => self.push_to(part),
+        }
+    }
+}
+
+fn push_comparison(part: &mut ParameterizedPart, column: &str, op: &str, value: &str) {
+    part.push_sql(column);
+    part.push_infix(op);
+    part.push_text(value);
+}
+
+// Clause kinds are declared in the order they must appear in the compiled
+// SQL, which is what gives clauses their canonical position regardless of
+// the order the builder methods were called in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ClauseKind {
+    Statement,
+    Values,
+    Set,
+    From,
+    Using,
+    Join,
+    Where,
+    GroupBy,
+    Having,
+    OrderBy,
+    Limit,
+    Offset,
+    OnConflict,
+    WhenMatched,
+    WhenNotMatched,
+    Returning,
+    Lock,
+    Raw,
+}
+
+impl ClauseKind {
+    fn keyword(&self) -> &'static str {
+        match self {
+            ClauseKind::Statement => "statement",
+            ClauseKind::Values => "VALUES",
+            ClauseKind::Set => "SET",
+            ClauseKind::From => "FROM",
+            ClauseKind::Using => "USING",
+            ClauseKind::Join => "JOIN",
+            ClauseKind::Where => "WHERE",
+            ClauseKind::GroupBy => "GROUP BY",
+            ClauseKind::Having => "HAVING",
+            ClauseKind::OrderBy => "ORDER BY",
+            ClauseKind::Limit => "LIMIT",
+            ClauseKind::Offset => "OFFSET",
+            ClauseKind::OnConflict => "ON CONFLICT",
+            ClauseKind::WhenMatched => "WHEN MATCHED",
+            ClauseKind::WhenNotMatched => "WHEN NOT MATCHED",
+            ClauseKind::Returning => "RETURNING",
+            ClauseKind::Lock => "FOR UPDATE/FOR SHARE",
+            ClauseKind::Raw => "raw",
+        }
+    }
+
+    // Joins and raw fragments can repeat and WHERE conditions are merged,
+    // every other clause may only appear once per statement.
+    fn is_unique(&self) -> bool {
+        !matches!(self, ClauseKind::Join | ClauseKind::Where | ClauseKind::Raw)
+    }
+}
+
+pub struct QueryBlock {
+    query_part: String,
+    secondary_part: Option<Box<QueryBlock>>,
+    statement_type: StatementType,
+    clause_kind: ClauseKind,
+    parameterized_part: Option<ParameterizedPart>,
+    // Set on nodes whose syntax only one dialect understands.
+    dialect: Option<Dialect>,
+    // Only meaningful on the head node, see `validate`.
+    allow_full_scan: bool,
+}
+
+impl QueryBlock {
+    fn new(query_part: String, statement_type: StatementType) -> Self {
+        QueryBlock {
+            query_part,
+            secondary_part: None,
+            statement_type,
+            clause_kind: ClauseKind::Statement,
+            parameterized_part: None,
+            dialect: None,
+            allow_full_scan: false,
+        }
+    }
+
+    fn from_part(part: ParameterizedPart, statement_type: StatementType) -> Self {
+        let mut query_block = QueryBlock::new(part.inline(), statement_type);
+        query_block.parameterized_part = Some(part);
+        query_block
+    }
+
+    pub fn query_part(&self) -> &str {
+        &self.query_part
+    }
+
+    pub fn secondary_part(&self) -> Option<&QueryBlock> {
+        self.secondary_part.as_deref()
+    }
+
+    pub fn statement_type(&self) -> &StatementType {
+        &self.statement_type
+    }
+
+    pub fn clause_kind(&self) -> ClauseKind {
+        self.clause_kind
+    }
+}
+
+// Unlinks the chain node by node, so dropping a very long chain does not
+// recurse once per clause.
+impl Drop for QueryBlock {
+    fn drop(&mut self) {
+        let mut next = self.secondary_part.take();
+        while let Some(mut node) = next {
+            next = node.secondary_part.take();
+        }
+    }
+}
+
+// Deep-clones the chain iteratively, for the same reason `Drop` unlinks it
+// iteratively.
+impl Clone for QueryBlock {
+    fn clone(&self) -> Self {
+        let mut nodes = Vec::new();
+        let mut node = Some(self);
+        while let Some(current) = node {
+            nodes.push(QueryBlock {
+                query_part: current.query_part.clone(),
+                secondary_part: None,
+                statement_type: current.statement_type.clone(),
+                clause_kind: current.clause_kind,
+                parameterized_part: current.parameterized_part.clone(),
+                dialect: current.dialect,
+                allow_full_scan: current.allow_full_scan,
+            });
+            node = current.secondary_part.as_deref();
+        }
+        let mut secondary_part = None;
+        while let Some(mut clone) = nodes.pop() {
+            clone.secondary_part = secondary_part;
+            secondary_part = Some(Box::new(clone));
+        }
+        *secondary_part.unwrap()
+    }
+}
+
+// Lists the chain's nodes in order instead of nesting each node in the one
+// before it, so formatting a very long chain does not recurse per clause.
+impl fmt::Debug for QueryBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Node<'a>(&'a QueryBlock);
+
+        impl fmt::Debug for Node<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("QueryBlock")
+                    .field("query_part", &self.0.query_part)
+                    .field("statement_type", &self.0.statement_type)
+                    .field("clause_kind", &self.0.clause_kind)
+                    .field("parameterized_part", &self.0.parameterized_part)
+                    .field("dialect", &self.0.dialect)
+                    .field("allow_full_scan", &self.0.allow_full_scan)
+                    .finish()
+            }
+        }
+
+        let mut nodes = f.debug_list();
+        let mut node = Some(self);
+        while let Some(current) = node {
+            nodes.entry(&Node(current));
+            node = current.secondary_part.as_deref();
+        }
+        nodes.finish()
+    }
+}
+
+// Compares the chains node by node.
+impl PartialEq for QueryBlock {
+    fn eq(&self, other: &Self) -> bool {
+        let mut left = Some(self);
+        let mut right = Some(other);
+        loop {
+            match (left, right) {
+                (None, None) => return true,
+                (Some(l), Some(r)) => {
+                    if l.query_part != r.query_part
+                        || l.statement_type != r.statement_type
+                        || l.clause_kind != r.clause_kind
+                        || l.parameterized_part != r.parameterized_part
+                        || l.dialect != r.dialect
+                        || l.allow_full_scan != r.allow_full_scan
+                    {
+                        return false;
+                    }
+                    left = l.secondary_part.as_deref();
+                    right = r.secondary_part.as_deref();
+                }
+                _ => return false,
+            }
+        }
+    }
+}
+
+// A chain is serialized as a tree mirroring the linked list, each node
+// holding the next one under `secondary_part`. The nesting grows by one level
+// per clause, so a long chain can exceed a format's nesting limit; with
+// serde_json that limit is lifted with `Deserializer::disable_recursion_limit`.
+#[derive(Serialize)]
+struct SerializedNode<'a> {
+    query_part: &'a str,
+    secondary_part: Option<&'a QueryBlock>,
+    statement_type: &'a StatementType,
+    clause_kind: ClauseKind,
+    parameterized_part: &'a Option<ParameterizedPart>,
+    dialect: Option<Dialect>,
+    allow_full_scan: bool,
+}
+
+#[derive(Deserialize)]
+struct DeserializedNode {
+    query_part: String,
+    secondary_part: Option<Box<QueryBlock>>,
+    statement_type: StatementType,
+    clause_kind: ClauseKind,
+    parameterized_part: Option<ParameterizedPart>,
+    dialect: Option<Dialect>,
+    allow_full_scan: bool,
+}
+
+impl Serialize for QueryBlock {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedNode {
+            query_part: &self.query_part,
+            secondary_part: self.secondary_part.as_deref(),
+            statement_type: &self.statement_type,
+            clause_kind: self.clause_kind,
+            parameterized_part: &self.parameterized_part,
+            dialect: self.dialect,
+            allow_full_scan: self.allow_full_scan,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for QueryBlock {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let node = DeserializedNode::deserialize(deserializer)?;
+        // The executors compile the parameterized form and `compile_statement`
+        // the query part, so a node where the two disagree is rejected rather
+        // than compiling to different statements.
+        if let Some(part) = &node.parameterized_part {
+            if part.inline() != node.query_part {
+                return Err(serde::de::Error::custom(format!(
+                    "query part `{}` does not match its parameterized form `{}`",
+                    node.query_part,
+                    part.inline()
+                )));
+            }
+        }
+        Ok(QueryBlock {
+            query_part: node.query_part,
+            secondary_part: node.secondary_part,
+            statement_type: node.statement_type,
+            clause_kind: node.clause_kind,
+            parameterized_part: node.parameterized_part,
+            dialect: node.dialect,
+            allow_full_scan: node.allow_full_scan,
+        })
+    }
+}
+
+impl fmt::Display for QueryBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&compile_statement(self))
+    }
+}
+
+// A value embedded in a query part. Text is kept unescaped and booleans
+// unspelled, so both can be rendered for the dialect they are finally
+// inlined for.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum Literal {
+    Number(String),
+    Text(String),
+    Bool(bool),
+}
+
+impl Literal {
+    fn inline(&self, dialect: Option<Dialect>) -> String {
+        match (self, dialect) {
+            (Literal::Number(value), _) => value.clone(),
+            (Literal::Text(value), Some(dialect)) => dialect.escape_literal(value),
+            (Literal::Text(value), None) => escape_literal(value),
+            (Literal::Bool(value), Some(dialect)) => dialect.boolean_literal(*value).to_string(),
+            (Literal::Bool(true), None) => "TRUE".to_string(),
+            (Literal::Bool(false), None) => "FALSE".to_string(),
+        }
+    }
+
+    // Booleans are bound as `1` and `0`, which Postgres, MySQL and SQLite
+    // all accept for a boolean column.
+    fn parameter(&self) -> String {
+        match self {
+            Literal::Number(value) | Literal::Text(value) => value.clone(),
+            Literal::Bool(value) => if *value { "1" } else { "0" }.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum Segment {
+    Sql(String),
+    // Emitted by the builder itself, so it is safe to recase.
+    Keyword(String),
+    Literal(Literal),
+}
+
+// A query part split into the SQL text around it, the keywords the builder
+// emitted and its literal values, so it can be rendered inline or with
+// positional placeholders and in either keyword case.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct ParameterizedPart {
+    segments: Vec<Segment>,
+}
+
+impl ParameterizedPart {
+    fn new() -> Self {
+        ParameterizedPart {
+            segments: Vec::new(),
+        }
+    }
+
+    fn from_sql(sql: &str) -> Self {
+        ParameterizedPart::new().sql(sql)
+    }
+
+    fn keyword(self, keyword: &str) -> Self {
+        let mut part = self;
+        part.push_keyword(keyword);
+        part
+    }
+
+    fn sql(self, sql: &str) -> Self {
+        let mut part = self;
+        part.push_sql(sql);
+        part
+    }
+
+    fn part(self, other: ParameterizedPart) -> Self {
+        let mut part = self;
+        part.append(other);
+        part
+    }
+
+    fn append(&mut self, other: ParameterizedPart) {
+        for segment in other.segments {
+            match segment {
+                Segment::Sql(sql) => self.push_sql(&sql),
+                segment => self.segments.push(segment),
+            }
+        }
+    }
+
+    fn push_sql(&mut self, sql: &str) {
+        if sql.is_empty() {
+            return;
+        }
+        match self.segments.last_mut() {
+            Some(Segment::Sql(last)) => last.push_str(sql),
+            _ => self.segments.push(Segment::Sql(sql.to_string())),
+        }
+    }
+
+    fn push_keyword(&mut self, keyword: &str) {
+        self.segments.push(Segment::Keyword(keyword.to_string()));
+    }
+
+    // A keyword between two operands, such as AND, with a space either side.
+    fn push_infix(&mut self, keyword: &str) {
+        self.push_sql(" ");
+        self.push_keyword(keyword);
+        self.push_sql(" ");
+    }
+
+    fn push_text(&mut self, value: &str) {
+        self.push_literal(Literal::Text(value.to_string()));
+    }
+
+    // A `Numeric` hint only leaves the value bare when it actually parses as
+    // a number, so a mislabelled value can never break out of the literal.
+    fn push_hinted(&mut self, value: &str, hint: LiteralHint) {
+        match hint {
+            LiteralHint::Numeric if is_numeric_literal(value) => {
+                self.push_literal(Literal::Number(value.to_string()))
+            }
+            _ => self.push_text(value),
+        }
+    }
+
+    fn push_literal(&mut self, literal: Literal) {
+        self.segments.push(Segment::Literal(literal));
+    }
+
+    // Drops the leading clause keyword and the space after it.
+    fn strip_keyword(mut self) -> Self {
+        if let Some(Segment::Keyword(_)) = self.segments.first() {
+            self.segments.remove(0);
+        }
+        if let Some(Segment::Sql(sql)) = self.segments.first_mut() {
+            if let Some(rest) = sql.strip_prefix(' ') {
+                *sql = rest.to_string();
+            }
+        }
+        self
+    }
+
+    fn parameters(&self) -> Vec<String> {
+        self.segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Literal(literal) => Some(literal.parameter()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn render(
+        &self,
+        case: KeywordCase,
+        mut placeholder: impl FnMut(usize, &Literal) -> String,
+    ) -> String {
+        let mut rendered = String::new();
+        let mut index = 0;
+        for segment in &self.segments {
+            match segment {
+                Segment::Sql(sql) => rendered.push_str(sql),
+                Segment::Keyword(keyword) => rendered.push_str(&case.apply(keyword)),
+                Segment::Literal(literal) => {
+                    rendered.push_str(&placeholder(index, literal));
+                    index += 1;
+                }
+            }
+        }
+        rendered
+    }
+
+    fn inline(&self) -> String {
+        self.render(KeywordCase::Upper, |_, literal| literal.inline(None))
+    }
+}
+
+pub struct Column {
+    pub name: String,
+    pub table: Option<String>,
+}
+
+impl Column {
+    pub fn new(name: &str) -> Self {
+        Column {
+            name: name.to_string(),
+            table: None,
+        }
+    }
+
+    pub fn of(table: &str, name: &str) -> Self {
+        Column {
+            name: name.to_string(),
+            table: Some(table.to_string()),
+        }
+    }
+
+    pub fn qualified(&self) -> String {
+        match &self.table {
+            Some(table) => format!("{}.{}", table, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Model {
+    pub name: String,
+    pub fields: Option<Vec<String>>,
+    pub alias: Option<String>,
+    pub schema: Option<String>,
+    pub primary_key: Option<Vec<String>>,
+    // When set, the table name, alias and field names are emitted as quoted
+    // identifiers, so fields must be plain column names, not expressions.
+    pub quote_identifiers: bool,
+}
+
+impl Model {
+    pub fn builder() -> ModelBuilder {
+        ModelBuilder::new()
+    }
+
+    pub fn with_columns(name: &str, columns: Vec<Column>) -> Self {
+        Model {
+            name: name.to_string(),
+            fields: Some(columns.iter().map(Column::qualified).collect()),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn identifier(&self, name: &str) -> String {
+        if self.quote_identifiers {
+            escape_identifier(name)
+        } else {
+            name.to_string()
+        }
+    }
+
+    // The schema and table are quoted separately, so `public.users` becomes
+    // `"public"."users"` rather than a single identifier containing a dot.
+    pub(crate) fn table_name(&self) -> String {
+        match &self.schema {
+            Some(schema) => format!(
+                "{}.{}",
+                self.identifier(schema),
+                self.identifier(&self.name)
+            ),
+            None => self.identifier(&self.name),
+        }
+    }
+
+    fn table_reference(&self) -> ParameterizedPart {
+        let table = ParameterizedPart::from_sql(&self.table_name());
+        match &self.alias {
+            Some(alias) => table
+                .sql(" ")
+                .keyword("AS")
+                .sql(&format!(" {}", self.identifier(alias))),
+            None => table,
+        }
+    }
+
+    fn projection(&self) -> String {
+        match &self.fields {
+            Some(fields) => self.field_list(fields),
+            None => "*".to_string(),
+        }
+    }
+
+    fn field_list(&self, fields: &[String]) -> String {
+        fields
+            .iter()
+            .map(|field| self.identifier(field))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+#[derive(Default)]
+pub struct ModelBuilder {
+    model: Model,
+}
+
+impl ModelBuilder {
+    pub fn new() -> Self {
+        ModelBuilder::default()
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.model.name = name.to_string();
+        self
+    }
+
+    pub fn fields(mut self, fields: &[&str]) -> Self {
+        self.model.fields = Some(fields.iter().map(|field| field.to_string()).collect());
+        self
+    }
+
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.model.alias = Some(alias.to_string());
+        self
+    }
+
+    pub fn schema(mut self, schema: &str) -> Self {
+        self.model.schema = Some(schema.to_string());
+        self
+    }
+
+    pub fn primary_key(mut self, columns: &[&str]) -> Self {
+        self.model.primary_key = Some(columns.iter().map(|column| column.to_string()).collect());
+        self
+    }
+
+    pub fn quote_identifiers(mut self, quote_identifiers: bool) -> Self {
+        self.model.quote_identifiers = quote_identifiers;
+        self
+    }
+
+    pub fn build(self) -> Model {
+        if self.model.name.is_empty() {
+            panic!("Model must have a name");
+        }
+        self.model
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum LiteralHint {
+    Text,
+    Numeric,
+}
+
+pub fn escape_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+pub fn format_literal(value: &str, hint: LiteralHint) -> String {
+    let mut part = ParameterizedPart::new();
+    part.push_hinted(value, hint);
+    part.inline()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Bool(bool),
+    Null,
+}
+
+impl Value {
+    pub fn to_sql(&self) -> String {
+        let mut part = ParameterizedPart::new();
+        self.push_to(&mut part);
+        part.inline()
+    }
+
+    // Numbers, text and booleans become parameters; NULL is a keyword and
+    // stays in the SQL. Non-finite floats have no bare SQL spelling, so they
+    // are quoted like text.
+    fn push_to(&self, part: &mut ParameterizedPart) {
+        match self {
+            Value::Int(value) => part.push_literal(Literal::Number(value.to_string())),
+            Value::Float(value) if value.is_finite() => {
+                part.push_literal(Literal::Number(value.to_string()))
+            }
+            Value::Float(value) => part.push_text(&value.to_string()),
+            Value::Text(value) => part.push_text(value),
+            Value::Bool(value) => part.push_literal(Literal::Bool(*value)),
+            Value::Null => part.push_keyword("NULL"),
+        }
+    }
+}
+
+fn is_numeric_literal(value: &str) -> bool {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    let mut parts = digits.splitn(2, '.');
+    let integer = parts.next().unwrap_or("");
+    let fraction = parts.next().unwrap_or("0");
+    !integer.is_empty()
+        && integer.chars().all(|c| c.is_ascii_digit())
+        && !fraction.is_empty()
+        && fraction.chars().all(|c| c.is_ascii_digit())
+}
+
+pub fn escape_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
 }
 
-struct QueryBlock {
-    pub query_part: String,
-    pub secondary_part: Option<Box<QueryBlock>>,
-    pub statement_type: StatementType,
-}
+pub trait SecondaryPart: Sized {
+    fn values(self, values: &Vec<String>) -> Self;
+    fn values_hinted(self, values: &Vec<(String, LiteralHint)>) -> Self;
+    fn values_many(self, rows: &Vec<Vec<String>>) -> Self;
+    fn values_typed(self, values: &Vec<Value>) -> Self;
+    fn where_clause(self, where_clause: &String) -> Self;
+    fn where_cond(self, cond: Condition) -> Self;
+    fn where_if(self, condition: bool, clause: &String) -> Self;
+    fn where_eq(self, column: &String, value: &Value) -> Self;
+    fn where_eq_all(self, filters: &HashMap<String, String>) -> Self;
+    fn keyset_page(self, column: &String, last_value: &String, limit: u64) -> Self;
+    fn set(self, arguments: &HashMap<String, String>) -> Self;
+    fn set_raw(self, arguments: &HashMap<String, String>) -> Self;
+    fn set_typed(self, arguments: &HashMap<String, Value>) -> Self;
+    fn set_case(self, column: &String, key: &String, mapping: &Vec<(String, String)>) -> Self;
+    fn order_by(self, columns: &Vec<String>) -> Self;
+    fn order_by_checked(self, column: &str, allowed: &[&str]) -> Result<Self, QueryError>;
+    fn order_by_with(self, columns: &Vec<(String, OrderDirection)>) -> Self;
+    fn order_by_with_nulls(
+        self,
+        columns: &Vec<(String, OrderDirection, Option<NullsPlacement>)>,
+    ) -> Self;
+    fn group_by(self, columns: &Vec<String>) -> Self;
+    fn having(self, condition: &String) -> Self;
+    fn limit(self, n: u64) -> Self;
+    fn offset(self, n: u64) -> Self;
+    fn join(self, table: &String, on: &String) -> Self;
+    fn join_kind(self, kind: JoinKind, table: &String, on: &String) -> Self;
+    fn cross_join(self, table: &String) -> Self;
+    fn join_lateral(self, subquery: QueryBlock, alias: &str, kind: JoinKind) -> Self;
+    fn where_in_subquery(self, column: &String, subquery: QueryBlock) -> Self;
+    fn where_in(self, column: &String, values: &Vec<String>) -> Self;
+    fn where_exists(self, subquery: QueryBlock) -> Self;
+    fn where_not_exists(self, subquery: QueryBlock) -> Self;
+    fn where_any(self, column: &String, op: &str, subquery: QueryBlock) -> Self;
+    fn where_all(self, column: &String, op: &str, subquery: QueryBlock) -> Self;
+    fn where_between(self, column: &String, low: &String, high: &String) -> Self;
+    fn where_like(self, column: &String, pattern: &String) -> Self;
+    fn where_ilike(self, column: &String, pattern: &String) -> Self;
+    fn where_null(self, column: &String) -> Self;
+    fn where_not_null(self, column: &String) -> Self;
+    fn returning(self, columns: &Vec<String>) -> Self;
+    fn from(self, table: &String) -> Self;
+    fn using(self, table: &String) -> Self;
+    fn raw(self, fragment: &String) -> Self;
+    fn for_update(self) -> Self;
+    fn allow_full_scan(self) -> Self;
+    fn for_share(self) -> Self;
+    fn on_conflict(self, target: &Vec<String>, updates: &HashMap<String, String>) -> Self;
+    fn on_duplicate_key_update(self, updates: &HashMap<String, String>) -> Self;
+    fn when_matched_update(self, updates: &HashMap<String, String>) -> Self;
+    fn when_not_matched_insert(self, columns: &Vec<String>, values: &Vec<String>) -> Self;
+}
+
+impl<C: ClauseChain> SecondaryPart for C {
+    fn values(self, values: &Vec<String>) -> Self {
+        let values = values
+            .iter()
+            .map(|value| (value.clone(), LiteralHint::Text))
+            .collect();
+        self.values_hinted(&values)
+    }
+
+    fn values_hinted(self, values: &Vec<(String, LiteralHint)>) -> Self {
+        let mut values_part = clause_part(ClauseKind::Values).sql(" (");
+        for (index, (value, hint)) in values.iter().enumerate() {
+            if index > 0 {
+                values_part.push_sql(", ");
+            }
+            values_part.push_hinted(value, *hint);
+        }
+        values_part.push_sql(")");
+        append_node(self, ClauseKind::Values, values_part)
+    }
+
+    fn values_typed(self, values: &Vec<Value>) -> Self {
+        let mut values_part = clause_part(ClauseKind::Values).sql(" (");
+        for (index, value) in values.iter().enumerate() {
+            if index > 0 {
+                values_part.push_sql(", ");
+            }
+            value.push_to(&mut values_part);
+        }
+        values_part.push_sql(")");
+        append_node(self, ClauseKind::Values, values_part)
+    }
+
+    fn values_many(self, rows: &Vec<Vec<String>>) -> Self {
+        if rows.is_empty() {
+            panic!("VALUES clause must have at least one row");
+        }
+        let mut values_part = clause_part(ClauseKind::Values).sql(" ");
+        for (row_index, row) in rows.iter().enumerate() {
+            if row_index > 0 {
+                values_part.push_sql(", ");
+            }
+            values_part.push_sql("(");
+            for (index, value) in row.iter().enumerate() {
+                if index > 0 {
+                    values_part.push_sql(", ");
+                }
+                values_part.push_text(value);
+            }
+            values_part.push_sql(")");
+        }
+        append_node(self, ClauseKind::Values, values_part)
+    }
+
+    fn set(self, arguments: &HashMap<String, String>) -> Self {
+        let mut set_part = clause_part(ClauseKind::Set).sql(" ");
+        for (index, (key, value)) in sorted_entries(arguments).into_iter().enumerate() {
+            if index > 0 {
+                set_part.push_sql(", ");
+            }
+            set_part.push_sql(&format!("{} = ", key));
+            set_part.push_text(value);
+        }
+        append_node(self, ClauseKind::Set, set_part)
+    }
+
+    fn set_typed(self, arguments: &HashMap<String, Value>) -> Self {
+        let mut set_part = clause_part(ClauseKind::Set).sql(" ");
+        for (index, (key, value)) in sorted_entries(arguments).into_iter().enumerate() {
+            if index > 0 {
+                set_part.push_sql(", ");
+            }
+            set_part.push_sql(&format!("{} = ", key));
+            value.push_to(&mut set_part);
+        }
+        append_node(self, ClauseKind::Set, set_part)
+    }
+
+    // Sets `column` per row from a (key, value) mapping on the `key` column.
+    // Rows whose key is not mapped would be set to NULL by the CASE, so the
+    // update is also restricted to the mapped keys with WHERE IN.
+    fn set_case(self, column: &String, key: &String, mapping: &Vec<(String, String)>) -> Self {
+        if mapping.is_empty() {
+            panic!("CASE mapping must have at least one entry");
+        }
+        let mut set_part = clause_part(ClauseKind::Set)
+            .sql(&format!(" {} = ", column))
+            .keyword("CASE")
+            .sql(&format!(" {}", key));
+        for (when, then) in mapping {
+            set_part.push_infix("WHEN");
+            set_part.push_text(when);
+            set_part.push_infix("THEN");
+            set_part.push_text(then);
+        }
+        set_part.push_sql(" ");
+        set_part.push_keyword("END");
+        let keys = mapping.iter().map(|(when, _)| when.clone()).collect();
+        append_node(self, ClauseKind::Set, set_part).where_in(key, &keys)
+    }
+
+    // Values are emitted verbatim so they can reference other columns, as in
+    // `SET x = b.y`; they are neither escaped nor parameterized.
+    fn set_raw(self, arguments: &HashMap<String, String>) -> Self {
+        let set_part = clause_part(ClauseKind::Set).sql(&format!(" {}", assignments(arguments)));
+        append_node(self, ClauseKind::Set, set_part)
+    }
+
+    // The condition is raw SQL and is kept verbatim: typed literals such as
+    // `DATE '2024-01-01'` or type modifiers such as `numeric(10, 2)` are not
+    // values, so nothing in it is parameterized. Pass values through
+    // `where_eq`, `where_cond` or the other helpers instead.
+    fn where_clause(self, where_clause: &String) -> Self {
+        append_where(self, ParameterizedPart::from_sql(where_clause))
+    }
+
+    fn where_cond(self, cond: Condition) -> Self {
+        let mut condition = ParameterizedPart::new();
+        cond.push_to(&mut condition);
+        append_where(self, condition)
+    }
+
+    // `col = NULL` is never true in SQL, so comparing against `Value::Null`
+    // renders `col IS NULL` instead.
+    fn where_eq(self, column: &String, value: &Value) -> Self {
+        let condition = match value {
+            Value::Null => is_null(column, false),
+            value => {
+                let mut condition = ParameterizedPart::from_sql(&format!("{} = ", column));
+                value.push_to(&mut condition);
+                condition
+            }
+        };
+        append_where(self, condition)
+    }
+
+    fn where_if(self, condition: bool, clause: &String) -> Self {
+        if condition {
+            self.where_clause(clause)
+        } else {
+            self
+        }
+    }
+
+    // Fetches the page after `last_value`, the cursor column's value on the
+    // last row of the previous page.
+    fn keyset_page(self, column: &String, last_value: &String, limit: u64) -> Self {
+        let mut condition = ParameterizedPart::new();
+        push_comparison(&mut condition, column, ">", last_value);
+        append_where(self, condition)
+            .order_by(&vec![column.clone()])
+            .limit(limit)
+    }
+
+    fn where_eq_all(self, filters: &HashMap<String, String>) -> Self {
+        if filters.is_empty() {
+            return self;
+        }
+        let mut conditions = ParameterizedPart::new();
+        for (index, (key, value)) in sorted_entries(filters).into_iter().enumerate() {
+            if index > 0 {
+                conditions.push_infix("AND");
+            }
+            push_comparison(&mut conditions, key, "=", value);
+        }
+        append_where(self, conditions)
+    }
+
+    fn order_by(self, columns: &Vec<String>) -> Self {
+        let columns = columns
+            .iter()
+            .map(|column| ParameterizedPart::from_sql(column))
+            .collect();
+        append_order_by(self, columns)
+    }
+
+    // Guards sort columns that come from user input: anything outside
+    // `allowed` is rejected rather than interpolated into the query.
+    fn order_by_checked(self, column: &str, allowed: &[&str]) -> Result<Self, QueryError> {
+        if !allowed.contains(&column) {
+            return Err(QueryError::DisallowedColumn {
+                column: column.to_string(),
+            });
+        }
+        Ok(self.order_by(&vec![column.to_string()]))
+    }
+
+    fn order_by_with(self, columns: &Vec<(String, OrderDirection)>) -> Self {
+        let columns = columns
+            .iter()
+            .map(|(column, direction)| (column.clone(), *direction, None))
+            .collect();
+        self.order_by_with_nulls(&columns)
+    }
+
+    fn order_by_with_nulls(
+        self,
+        columns: &Vec<(String, OrderDirection, Option<NullsPlacement>)>,
+    ) -> Self {
+        let columns = columns
+            .iter()
+            .map(|(column, direction, nulls)| {
+                let column = ParameterizedPart::from_sql(&format!("{} ", column))
+                    .keyword(direction.keyword());
+                match nulls {
+                    Some(nulls) => column.sql(" ").keyword(nulls.keyword()),
+                    None => column,
+                }
+            })
+            .collect();
+        append_order_by(self, columns)
+    }
+
+    fn group_by(self, columns: &Vec<String>) -> Self {
+        if columns.is_empty() {
+            return self;
+        }
+        let group_by_part =
+            clause_part(ClauseKind::GroupBy).sql(&format!(" {}", columns.join(", ")));
+        append_node(self, ClauseKind::GroupBy, group_by_part)
+    }
+
+    fn having(self, condition: &String) -> Self {
+        if !self.has_clause(ClauseKind::GroupBy) {
+            panic!("HAVING clause must follow a GROUP BY clause");
+        }
+        let having_part = clause_part(ClauseKind::Having).sql(&format!(" {}", condition));
+        append_node(self, ClauseKind::Having, having_part)
+    }
+
+    fn limit(self, n: u64) -> Self {
+        let limit_part = clause_part(ClauseKind::Limit).sql(&format!(" {}", n));
+        append_node(self, ClauseKind::Limit, limit_part)
+    }
+
+    fn offset(self, n: u64) -> Self {
+        let offset_part = clause_part(ClauseKind::Offset).sql(&format!(" {}", n));
+        append_node(self, ClauseKind::Offset, offset_part)
+    }
+
+    fn join(self, table: &String, on: &String) -> Self {
+        self.join_kind(JoinKind::Inner, table, on)
+    }
+
+    fn join_kind(self, kind: JoinKind, table: &String, on: &String) -> Self {
+        let join_part = ParameterizedPart::new()
+            .keyword(kind.keyword())
+            .sql(&format!(" {} ", table))
+            .keyword("ON")
+            .sql(&format!(" {}", on));
+        append_node(self, ClauseKind::Join, join_part)
+    }
+
+    fn cross_join(self, table: &String) -> Self {
+        let join_part = ParameterizedPart::new()
+            .keyword("CROSS JOIN")
+            .sql(&format!(" {}", table));
+        append_node(self, ClauseKind::Join, join_part)
+    }
+
+    // The subquery correlates with the outer row itself, so the join
+    // condition is always `ON true`.
+    fn join_lateral(self, subquery: QueryBlock, alias: &str, kind: JoinKind) -> Self {
+        let join_part = ParameterizedPart::new()
+            .keyword(kind.keyword())
+            .sql(" ")
+            .keyword("LATERAL")
+            .sql(" (")
+            .part(statement_part(&subquery))
+            .sql(") ")
+            .keyword("AS")
+            .sql(&format!(" {} ", alias))
+            .keyword("ON")
+            .sql(" true");
+        append_node(self, ClauseKind::Join, join_part)
+    }
+
+    fn where_in_subquery(self, column: &String, subquery: QueryBlock) -> Self {
+        let prefix = ParameterizedPart::from_sql(&format!("{} ", column)).keyword("IN");
+        append_where(self, nested_condition(prefix, &subquery))
+    }
+
+    // An empty list renders as `IN (NULL)`, which matches no rows instead of
+    // producing the invalid `IN ()`.
+    fn where_in(self, column: &String, values: &Vec<String>) -> Self {
+        let mut condition = ParameterizedPart::from_sql(&format!("{} ", column))
+            .keyword("IN")
+            .sql(" (");
+        if values.is_empty() {
+            condition.push_keyword("NULL");
+        }
+        for (index, value) in values.iter().enumerate() {
+            if index > 0 {
+                condition.push_sql(", ");
+            }
+            condition.push_text(value);
+        }
+        condition.push_sql(")");
+        append_where(self, condition)
+    }
+
+    fn where_exists(self, subquery: QueryBlock) -> Self {
+        let prefix = ParameterizedPart::new().keyword("EXISTS");
+        append_where(self, nested_condition(prefix, &subquery))
+    }
+
+    fn where_not_exists(self, subquery: QueryBlock) -> Self {
+        let prefix = ParameterizedPart::new().keyword("NOT EXISTS");
+        append_where(self, nested_condition(prefix, &subquery))
+    }
+
+    fn where_any(self, column: &String, op: &str, subquery: QueryBlock) -> Self {
+        quantified_comparison(self, column, op, "ANY", subquery)
+    }
+
+    fn where_all(self, column: &String, op: &str, subquery: QueryBlock) -> Self {
+        quantified_comparison(self, column, op, "ALL", subquery)
+    }
+
+    // Numeric bounds stay bare, anything else is quoted like text.
+    fn where_between(self, column: &String, low: &String, high: &String) -> Self {
+        let mut condition = ParameterizedPart::from_sql(&format!("{} ", column))
+            .keyword("BETWEEN")
+            .sql(" ");
+        condition.push_hinted(low, LiteralHint::Numeric);
+        condition.push_infix("AND");
+        condition.push_hinted(high, LiteralHint::Numeric);
+        append_where(self, condition)
+    }
+
+    fn where_like(self, column: &String, pattern: &String) -> Self {
+        let mut condition = ParameterizedPart::new();
+        push_comparison(&mut condition, column, "LIKE", pattern);
+        append_where(self, condition)
+    }
+
+    fn where_ilike(self, column: &String, pattern: &String) -> Self {
+        let mut condition = ParameterizedPart::new();
+        push_comparison(&mut condition, column, "ILIKE", pattern);
+        append_where(self, condition)
+    }
+
+    fn where_null(self, column: &String) -> Self {
+        append_where(self, is_null(column, false))
+    }
+
+    fn where_not_null(self, column: &String) -> Self {
+        append_where(self, is_null(column, true))
+    }
+
+    fn returning(self, columns: &Vec<String>) -> Self {
+        if matches!(self.statement_type(), StatementType::Select) {
+            panic!("RETURNING clause is only valid for INSERT, UPDATE and DELETE");
+        }
+        let returning_part =
+            clause_part(ClauseKind::Returning).sql(&format!(" {}", columns.join(", ")));
+        append_node(self, ClauseKind::Returning, returning_part)
+    }
+
+    fn from(self, table: &String) -> Self {
+        if !matches!(self.statement_type(), StatementType::Update) {
+            panic!("FROM clause can only be added to UPDATE statements");
+        }
+        let from_part = clause_part(ClauseKind::From).sql(&format!(" {}", table));
+        append_node(self, ClauseKind::From, from_part)
+    }
+
+    fn using(self, table: &String) -> Self {
+        if !matches!(self.statement_type(), StatementType::Delete) {
+            panic!("USING clause can only be added to DELETE statements");
+        }
+        let using_part = clause_part(ClauseKind::Using).sql(&format!(" {}", table));
+        append_node(self, ClauseKind::Using, using_part)
+    }
+
+    // Appends `fragment` verbatim at the very end of the statement. It is
+    // neither escaped nor parameterized, so it must never contain user input.
+    fn raw(self, fragment: &String) -> Self {
+        append_node(self, ClauseKind::Raw, ParameterizedPart::from_sql(fragment))
+    }
+
+    fn for_update(self) -> Self {
+        lock(self, "FOR UPDATE")
+    }
+
+    fn allow_full_scan(mut self) -> Self {
+        if let Some(head) = self.find_clause_mut(ClauseKind::Statement) {
+            head.allow_full_scan = true;
+        }
+        self
+    }
+
+    fn for_share(self) -> Self {
+        lock(self, "FOR SHARE")
+    }
+
+    // Without a target DO NOTHING applies to any conflict; Postgres only
+    // accepts DO UPDATE for a named target.
+    fn on_conflict(self, target: &Vec<String>, updates: &HashMap<String, String>) -> Self {
+        if target.is_empty() && !updates.is_empty() {
+            panic!("ON CONFLICT DO UPDATE needs a conflict target");
+        }
+        let on_conflict_part = if target.is_empty() {
+            clause_part(ClauseKind::OnConflict).sql(" ")
+        } else {
+            clause_part(ClauseKind::OnConflict).sql(&format!(" ({}) ", target.join(", ")))
+        };
+        let on_conflict_part = if updates.is_empty() {
+            on_conflict_part.keyword("DO NOTHING")
+        } else {
+            on_conflict_part
+                .keyword("DO UPDATE SET")
+                .sql(&format!(" {}", assignments(updates)))
+        };
+        append_node(self, ClauseKind::OnConflict, on_conflict_part)
+    }
+
+    // MySQL's counterpart to ON CONFLICT, so it shares that clause's slot.
+    fn on_duplicate_key_update(self, updates: &HashMap<String, String>) -> Self {
+        let on_duplicate_part = ParameterizedPart::new()
+            .keyword("ON DUPLICATE KEY UPDATE")
+            .sql(&format!(" {}", assignments(updates)));
+        let mut statement = append_node(self, ClauseKind::OnConflict, on_duplicate_part);
+        if let Some(node) = statement.find_clause_mut(ClauseKind::OnConflict) {
+            node.dialect = Some(Dialect::MySQL);
+        }
+        statement
+    }
+
+    // MERGE actions usually refer to the source row, as in `SET x = s.x`, so
+    // their values are emitted verbatim.
+    fn when_matched_update(self, updates: &HashMap<String, String>) -> Self {
+        if !matches!(self.statement_type(), StatementType::Merge) {
+            panic!("WHEN MATCHED clause can only be added to MERGE statements");
+        }
+        let matched_part = clause_part(ClauseKind::WhenMatched)
+            .sql(" ")
+            .keyword("THEN UPDATE SET")
+            .sql(&format!(" {}", assignments(updates)));
+        append_node(self, ClauseKind::WhenMatched, matched_part)
+    }
+
+    fn when_not_matched_insert(self, columns: &Vec<String>, values: &Vec<String>) -> Self {
+        if !matches!(self.statement_type(), StatementType::Merge) {
+            panic!("WHEN NOT MATCHED clause can only be added to MERGE statements");
+        }
+        let not_matched_part = clause_part(ClauseKind::WhenNotMatched)
+            .sql(" ")
+            .keyword("THEN INSERT")
+            .sql(&format!(" ({}) ", columns.join(", ")))
+            .keyword("VALUES")
+            .sql(&format!(" ({})", values.join(", ")));
+        append_node(self, ClauseKind::WhenNotMatched, not_matched_part)
+    }
+}
+
+const COMPARISON_OPERATORS: [&str; 7] = ["=", "<>", "!=", "<", "<=", ">", ">="];
+
+fn quantified_comparison<C: ClauseChain>(
+    statement: C,
+    column: &String,
+    op: &str,
+    quantifier: &str,
+    subquery: QueryBlock,
+) -> C {
+    if !COMPARISON_OPERATORS.contains(&op) {
+        panic!("unsupported comparison operator `{}`", op);
+    }
+    let prefix = ParameterizedPart::from_sql(&format!("{} {} ", column, op)).keyword(quantifier);
+    append_where(statement, nested_condition(prefix, &subquery))
+}
+
+// `prefix (subquery)`, keeping the subquery's literals as parameters so
+// they are numbered along with the outer statement's.
+fn nested_condition(prefix: ParameterizedPart, subquery: &QueryBlock) -> ParameterizedPart {
+    prefix.sql(" (").part(statement_part(subquery)).sql(")")
+}
+
+fn is_null(column: &str, negated: bool) -> ParameterizedPart {
+    let keyword = if negated { "IS NOT NULL" } else { "IS NULL" };
+    ParameterizedPart::from_sql(&format!("{} ", column)).keyword(keyword)
+}
+
+fn append_order_by<C: ClauseChain>(statement: C, columns: Vec<ParameterizedPart>) -> C {
+    if columns.is_empty() {
+        return statement;
+    }
+    let mut order_by_part = clause_part(ClauseKind::OrderBy).sql(" ");
+    for (index, column) in columns.into_iter().enumerate() {
+        if index > 0 {
+            order_by_part.push_sql(", ");
+        }
+        order_by_part.append(column);
+    }
+    append_node(statement, ClauseKind::OrderBy, order_by_part)
+}
+
+fn lock<C: ClauseChain>(statement: C, lock_str: &str) -> C {
+    if !matches!(statement.statement_type(), StatementType::Select) {
+        panic!("{} clause can only be added to SELECT statements", lock_str);
+    }
+    append_node(
+        statement,
+        ClauseKind::Lock,
+        ParameterizedPart::new().keyword(lock_str),
+    )
+}
+
+// Map arguments are emitted in key order, so the compiled SQL, and with it
+// the fingerprint, does not depend on the map's iteration order.
+fn sorted_entries<V>(arguments: &HashMap<String, V>) -> Vec<(&String, &V)> {
+    let mut entries: Vec<(&String, &V)> = arguments.iter().collect();
+    entries.sort_by(|left, right| left.0.cmp(right.0));
+    entries
+}
+
+fn assignments(arguments: &HashMap<String, String>) -> String {
+    sorted_entries(arguments)
+        .into_iter()
+        .map(|(key, value)| format!("{} = {}", key, value))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+pub fn select(model: &Model) -> QueryBlock {
+    let part = ParameterizedPart::new()
+        .keyword("SELECT")
+        .sql(&format!(" {} ", model.projection()))
+        .keyword("FROM")
+        .sql(" ")
+        .part(model.table_reference());
+    QueryBlock::from_part(part, StatementType::Select)
+}
+
+// Builds a select over user-chosen columns, rejecting any that are not in
+// `allowed`. An empty request selects every allowed column rather than `*`,
+// so columns outside the list can never be fetched.
+pub fn select_checked(
+    name: &str,
+    requested: &[&str],
+    allowed: &[&str],
+) -> Result<QueryBlock, QueryError> {
+    if let Some(column) = requested.iter().find(|column| !allowed.contains(column)) {
+        return Err(QueryError::DisallowedColumn {
+            column: column.to_string(),
+        });
+    }
+    let fields = if requested.is_empty() {
+        allowed
+    } else {
+        requested
+    };
+    let model = Model {
+        name: name.to_string(),
+        fields: Some(fields.iter().map(|field| field.to_string()).collect()),
+        ..Default::default()
+    };
+    Ok(select(&model))
+}
+
+pub fn select_from_subquery(
+    subquery: QueryBlock,
+    alias: &str,
+    fields: Option<Vec<String>>,
+) -> QueryBlock {
+    if alias.is_empty() {
+        panic!("derived table must have an alias");
+    }
+    let fields = match fields {
+        Some(fields) => fields.join(", "),
+        None => "*".to_string(),
+    };
+    let part = ParameterizedPart::new()
+        .keyword("SELECT")
+        .sql(&format!(" {} ", fields))
+        .keyword("FROM")
+        .sql(" (")
+        .part(statement_part(&subquery))
+        .sql(") ")
+        .keyword("AS")
+        .sql(&format!(" {}", alias));
+    QueryBlock::from_part(part, StatementType::Select)
+}
+
+pub fn select_distinct(model: &Model) -> QueryBlock {
+    let part = ParameterizedPart::new()
+        .keyword("SELECT DISTINCT")
+        .sql(&format!(" {} ", model.projection()))
+        .keyword("FROM")
+        .sql(" ")
+        .part(model.table_reference());
+    QueryBlock::from_part(part, StatementType::Select)
+}
+
+pub fn select_distinct_on(model: &Model, columns: &Vec<String>) -> QueryBlock {
+    let part = ParameterizedPart::new()
+        .keyword("SELECT DISTINCT ON")
+        .sql(&format!(
+            " ({}) {} ",
+            columns.join(", "),
+            model.projection()
+        ))
+        .keyword("FROM")
+        .sql(" ")
+        .part(model.table_reference());
+    let mut query_block = QueryBlock::from_part(part, StatementType::Select);
+    query_block.dialect = Some(Dialect::Postgres);
+    query_block
+}
+
+pub fn update(model: &Model) -> Result<QueryBlock, QueryError> {
+    match &model.fields {
+        Some(_) => {
+            let part = ParameterizedPart::new()
+                .keyword("UPDATE")
+                .sql(" ")
+                .part(model.table_reference());
+            Ok(QueryBlock::from_part(part, StatementType::Update))
+        }
+        None => Err(QueryError::MissingFields {
+            model: model.name.clone(),
+        }),
+    }
+}
+
+pub fn delete(model: &Model) -> QueryBlock {
+    let part = ParameterizedPart::new()
+        .keyword("DELETE FROM")
+        .sql(" ")
+        .part(model.table_reference());
+    QueryBlock::from_part(part, StatementType::Delete)
+}
+
+pub fn truncate(model: &Model) -> QueryBlock {
+    let part = ParameterizedPart::new()
+        .keyword("TRUNCATE TABLE")
+        .sql(&format!(" {}", model.table_name()));
+    QueryBlock::from_part(part, StatementType::Truncate)
+}
+
+// CASCADE also truncates tables holding foreign keys to this one. Postgres
+// only.
+pub fn truncate_cascade(model: &Model) -> QueryBlock {
+    let part = ParameterizedPart::new()
+        .keyword("TRUNCATE TABLE")
+        .sql(&format!(" {} ", model.table_name()))
+        .keyword("CASCADE");
+    QueryBlock::from_part(part, StatementType::Truncate)
+}
+
+// Wraps SQL the builder does not model, such as the DDL strings from the
+// `ddl` module, so it can be batched or executed like any other statement.
+// The SQL is used verbatim.
+pub fn raw_statement(sql: &str) -> QueryBlock {
+    QueryBlock::new(sql.to_string(), StatementType::Raw)
+}
+
+pub fn insert(model: &Model) -> QueryBlock {
+    insert_with(model, InsertMode::Default)
+}
+
+// The OR REPLACE and OR IGNORE forms are SQLite syntax.
+pub fn insert_with(model: &Model, mode: InsertMode) -> QueryBlock {
+    let mut part = ParameterizedPart::new()
+        .keyword(mode.keyword())
+        .sql(" ")
+        .part(model.table_reference());
+    if let Some(fields) = &model.fields {
+        part.push_sql(&format!(" ({})", model.field_list(fields)));
+    }
+    let mut query_block = QueryBlock::from_part(part, StatementType::Insert);
+    if !matches!(mode, InsertMode::Default) {
+        query_block.dialect = Some(Dialect::Sqlite);
+    }
+    query_block
+}
+
+pub fn merge(target: &Model, source: &str, on: &String) -> QueryBlock {
+    let part = ParameterizedPart::new()
+        .keyword("MERGE INTO")
+        .sql(" ")
+        .part(target.table_reference())
+        .sql(" ")
+        .keyword("USING")
+        .sql(&format!(" {} ", source))
+        .keyword("ON")
+        .sql(&format!(" {}", on));
+    QueryBlock::from_part(part, StatementType::Merge)
+}
+
+pub fn insert_select(model: &Model, source: QueryBlock) -> QueryBlock {
+    let part = statement_part(&insert(model))
+        .sql(" ")
+        .part(statement_part(&source));
+    QueryBlock::from_part(part, StatementType::Insert)
+}
+
+// Wraps the base query as `SELECT COUNT(*) FROM (...) AS sub` rather than
+// rewriting its projection, so DISTINCT, GROUP BY and joins are counted
+// exactly as the page sees them. ORDER BY, LIMIT and OFFSET are left out of
+// the subquery since they would only limit the total. `base` is not
+// modified.
+pub fn count_query(base: &QueryBlock) -> QueryBlock {
+    if !matches!(base.statement_type, StatementType::Select) {
+        panic!("count query can only be derived from a SELECT statement");
+    }
+    let inner = ordered_nodes(base).into_iter().filter(|node| {
+        !matches!(
+            node.clause_kind,
+            ClauseKind::OrderBy | ClauseKind::Limit | ClauseKind::Offset
+        )
+    });
+    let part = ParameterizedPart::new()
+        .keyword("SELECT")
+        .sql(" COUNT(*) ")
+        .keyword("FROM")
+        .sql(" (")
+        .part(join_parts(inner))
+        .sql(") ")
+        .keyword("AS")
+        .sql(" sub");
+    QueryBlock::from_part(part, StatementType::Select)
+}
+
+pub fn union(left: QueryBlock, right: QueryBlock, all: bool) -> QueryBlock {
+    let keyword = if all { "UNION ALL" } else { "UNION" };
+    set_operation(left, right, keyword)
+}
+
+pub fn intersect(left: QueryBlock, right: QueryBlock) -> QueryBlock {
+    set_operation(left, right, "INTERSECT")
+}
+
+pub fn except(left: QueryBlock, right: QueryBlock) -> QueryBlock {
+    set_operation(left, right, "EXCEPT")
+}
+
+// The CTE is prefixed to the main query's head node and its clauses stay
+// nodes of their own, so clauses chained onto the result merge with and are
+// checked against them as they would be on the main query.
+pub fn with(name: &String, body: QueryBlock, mut main: QueryBlock) -> QueryBlock {
+    let head = main
+        .parameterized_part
+        .take()
+        .unwrap_or_else(|| ParameterizedPart::from_sql(&main.query_part));
+    let part = ParameterizedPart::new()
+        .keyword("WITH")
+        .sql(&format!(" {} ", name))
+        .keyword("AS")
+        .sql(" (")
+        .part(statement_part(&body))
+        .sql(") ")
+        .part(head);
+    main.query_part = part.inline();
+    main.parameterized_part = Some(part);
+    main
+}
+
+fn set_operation(left: QueryBlock, right: QueryBlock, keyword: &str) -> QueryBlock {
+    let mut part = statement_part(&left);
+    part.push_infix(keyword);
+    part.append(statement_part(&right));
+    QueryBlock::from_part(part, left.statement_type.clone())
+}
+
+pub fn count(col: &str) -> String {
+    format!("COUNT({})", col)
+}
+
+pub fn sum(col: &str) -> String {
+    format!("SUM({})", col)
+}
+
+pub fn avg(col: &str) -> String {
+    format!("AVG({})", col)
+}
+
+pub fn min(col: &str) -> String {
+    format!("MIN({})", col)
+}
+
+pub fn max(col: &str) -> String {
+    format!("MAX({})", col)
+}
+
+pub fn string_agg(col: &str, sep: &str) -> String {
+    format!("STRING_AGG({}, {})", col, escape_literal(sep))
+}
+
+// MySQL's spelling of `string_agg`.
+pub fn group_concat(col: &str, sep: &str) -> String {
+    format!("GROUP_CONCAT({} SEPARATOR {})", col, escape_literal(sep))
+}
+
+pub fn alias(expr: &str, name: &str) -> String {
+    format!("{} AS {}", expr, name)
+}
+
+pub fn cast(expr: &str, ty: &str) -> String {
+    format!("CAST({} AS {})", expr, ty)
+}
+
+// SQLite rejects COALESCE with a single argument, so anything short of two
+// is treated as a builder mistake rather than rendered.
+pub fn coalesce(args: &Vec<String>) -> String {
+    if args.len() < 2 {
+        panic!("COALESCE requires at least two arguments");
+    }
+    format!("COALESCE({})", args.join(", "))
+}
+
+pub fn nullif(a: &str, b: &str) -> String {
+    format!("NULLIF({}, {})", a, b)
+}
+
+pub fn window(func: &str, partition_by: &Vec<String>, order_by: &Vec<String>) -> String {
+    let mut clauses = Vec::new();
+    if !partition_by.is_empty() {
+        clauses.push(format!("PARTITION BY {}", partition_by.join(", ")));
+    }
+    if !order_by.is_empty() {
+        clauses.push(format!("ORDER BY {}", order_by.join(", ")));
+    }
+    format!("{} OVER ({})", func, clauses.join(" "))
+}
+
+#[derive(Default)]
+pub struct CaseExpr {
+    arms: Vec<(String, String)>,
+    else_value: Option<String>,
+}
+
+impl CaseExpr {
+    pub fn new() -> Self {
+        CaseExpr::default()
+    }
+
+    pub fn when(mut self, cond: &str, result: &str) -> Self {
+        self.arms.push((cond.to_string(), result.to_string()));
+        self
+    }
+
+    pub fn else_(mut self, value: &str) -> Self {
+        self.else_value = Some(value.to_string());
+        self
+    }
+
+    pub fn to_sql(&self) -> String {
+        if self.arms.is_empty() {
+            panic!("CASE expression must have at least one WHEN arm");
+        }
+        let mut sql = String::from("CASE");
+        for (cond, result) in &self.arms {
+            sql.push_str(&format!(" WHEN {} THEN {}", cond, result));
+        }
+        if let Some(value) = &self.else_value {
+            sql.push_str(&format!(" ELSE {}", value));
+        }
+        sql.push_str(" END");
+        sql
+    }
+}
+
+// A clause part opening with the clause's own keyword.
+fn clause_part(clause_kind: ClauseKind) -> ParameterizedPart {
+    ParameterizedPart::new().keyword(clause_kind.keyword())
+}
+
+fn append_node<C: ClauseChain>(
+    mut statement: C,
+    clause_kind: ClauseKind,
+    part: ParameterizedPart,
+) -> C {
+    check_duplicate_clause(&statement, clause_kind);
+    let mut query_block = QueryBlock::from_part(part, statement.statement_type().clone());
+    query_block.clause_kind = clause_kind;
+    statement.push_clause(query_block);
+    statement
+}
+
+fn check_duplicate_clause<C: ClauseChain>(statement: &C, clause_kind: ClauseKind) {
+    if clause_kind.is_unique() && statement.has_clause(clause_kind) {
+        panic!("duplicate {} clause", clause_kind.keyword());
+    }
+}
+
+// A second WHERE condition is merged into the existing WHERE node with AND
+// instead of emitting the keyword twice.
+fn append_where<C: ClauseChain>(mut statement: C, condition: ParameterizedPart) -> C {
+    match statement.find_clause_mut(ClauseKind::Where) {
+        Some(node) => {
+            let previous = node
+                .parameterized_part
+                .take()
+                .unwrap_or_else(|| ParameterizedPart::from_sql(&node.query_part));
+            let mut merged = clause_part(ClauseKind::Where)
+                .sql(" ")
+                .part(parenthesize_or(previous.strip_keyword()));
+            merged.push_infix("AND");
+            merged.append(parenthesize_or(condition));
+            node.query_part = merged.inline();
+            node.parameterized_part = Some(merged);
+            statement
+        }
+        None => {
+            let where_part = clause_part(ClauseKind::Where).sql(" ").part(condition);
+            append_node(statement, ClauseKind::Where, where_part)
+        }
+    }
+}
+
+fn parenthesize_or(condition: ParameterizedPart) -> ParameterizedPart {
+    if !has_top_level_or(&condition.inline()) {
+        return condition;
+    }
+    let mut wrapped = ParameterizedPart::from_sql("(");
+    wrapped.append(condition);
+    wrapped.push_sql(")");
+    wrapped
+}
+
+fn has_top_level_or(sql: &str) -> bool {
+    let mut depth = 0;
+    let mut in_literal = false;
+    let mut word = String::new();
+    for current in sql.chars() {
+        if in_literal {
+            in_literal = current != '\'';
+            continue;
+        }
+        match current {
+            '\'' => in_literal = true,
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if current.is_alphanumeric() || current == '_' {
+            word.push(current);
+            continue;
+        }
+        if depth == 0 && word.eq_ignore_ascii_case("OR") {
+            return true;
+        }
+        word.clear();
+    }
+    depth == 0 && word.eq_ignore_ascii_case("OR")
+}
+
+// The storage the `SecondaryPart` methods build on. `QueryBlock` walks its
+// linked list to reach the tail, so every append is O(n); `QueryBuilder`
+// keeps the clauses in a vector instead, which keeps appends O(1) when a
+// long chain is generated.
+pub trait ClauseChain: Sized {
+    fn statement_type(&self) -> &StatementType;
+    fn has_clause(&self, clause_kind: ClauseKind) -> bool;
+    fn find_clause_mut(&mut self, clause_kind: ClauseKind) -> Option<&mut QueryBlock>;
+    fn push_clause(&mut self, clause: QueryBlock);
+}
+
+impl ClauseChain for QueryBlock {
+    fn statement_type(&self) -> &StatementType {
+        &self.statement_type
+    }
+
+    fn has_clause(&self, clause_kind: ClauseKind) -> bool {
+        let mut node = Some(self);
+        while let Some(current) = node {
+            if current.clause_kind == clause_kind {
+                return true;
+            }
+            node = current.secondary_part.as_deref();
+        }
+        false
+    }
+
+    fn find_clause_mut(&mut self, clause_kind: ClauseKind) -> Option<&mut QueryBlock> {
+        let mut node = Some(self);
+        while let Some(current) = node {
+            if current.clause_kind == clause_kind {
+                return Some(current);
+            }
+            node = current.secondary_part.as_deref_mut();
+        }
+        None
+    }
+
+    fn push_clause(&mut self, clause: QueryBlock) {
+        let latest_node = traverse_to_the_latest_node(self);
+        latest_node.secondary_part = Some(Box::new(clause));
+    }
+}
+
+pub struct QueryBuilder {
+    statement: QueryBlock,
+    clauses: Vec<QueryBlock>,
+}
+
+impl QueryBuilder {
+    pub fn new(mut statement: QueryBlock) -> Self {
+        let mut clauses = Vec::new();
+        let mut next = statement.secondary_part.take();
+        while let Some(mut clause) = next {
+            next = clause.secondary_part.take();
+            clauses.push(*clause);
+        }
+        QueryBuilder { statement, clauses }
+    }
+
+    pub fn build(mut self) -> QueryBlock {
+        let mut secondary_part = None;
+        while let Some(mut clause) = self.clauses.pop() {
+            clause.secondary_part = secondary_part;
+            secondary_part = Some(Box::new(clause));
+        }
+        self.statement.secondary_part = secondary_part;
+        self.statement
+    }
+}
+
+impl ClauseChain for QueryBuilder {
+    fn statement_type(&self) -> &StatementType {
+        &self.statement.statement_type
+    }
+
+    fn has_clause(&self, clause_kind: ClauseKind) -> bool {
+        self.statement.clause_kind == clause_kind
+            || self
+                .clauses
+                .iter()
+                .any(|clause| clause.clause_kind == clause_kind)
+    }
+
+    fn find_clause_mut(&mut self, clause_kind: ClauseKind) -> Option<&mut QueryBlock> {
+        if self.statement.clause_kind == clause_kind {
+            return Some(&mut self.statement);
+        }
+        self.clauses
+            .iter_mut()
+            .find(|clause| clause.clause_kind == clause_kind)
+    }
+
+    fn push_clause(&mut self, clause: QueryBlock) {
+        self.clauses.push(clause);
+    }
+}
+
+fn traverse_to_the_latest_node(statement: &mut QueryBlock) -> &mut QueryBlock {
+    let mut ret = statement;
+    while ret.secondary_part.is_some() {
+        ret = ret.secondary_part.as_mut().unwrap();
+    }
+    ret
+}
+
+pub fn compile_parameterized(statement: &QueryBlock) -> (String, Vec<String>) {
+    let part = statement_part(statement);
+    let sql = part.render(KeywordCase::Upper, |index, _| format!("${}", index + 1));
+    (sql, part.parameters())
+}
+
+// The whole statement as one part, so a statement nested inside another
+// keeps its literals as parameters of the outer one.
+fn statement_part(statement: &QueryBlock) -> ParameterizedPart {
+    join_parts(ordered_nodes(statement).into_iter())
+}
+
+fn join_parts<'a>(nodes: impl Iterator<Item = &'a QueryBlock>) -> ParameterizedPart {
+    let mut joined = ParameterizedPart::new();
+    for (index, node) in nodes.enumerate() {
+        if index > 0 {
+            joined.push_sql(" ");
+        }
+        match &node.parameterized_part {
+            Some(part) => joined.append(part.clone()),
+            None => joined.push_sql(&node.query_part),
+        }
+    }
+    joined
+}
+
+// Hashes the parameterized SQL, so queries differing only in their literal
+// values share a fingerprint. The value is stable within a process, which is
+// what a prepared-statement cache keyed on it needs. Only values the builder
+// binds are ignored: text given to `where_clause` or `raw` is kept verbatim,
+// so `where_clause("id = 1")` and `where_clause("id = 2")` fingerprint
+// differently. Use `where_eq` or `where_cond` for values that vary.
+pub fn fingerprint(statement: &QueryBlock) -> u64 {
+    let (sql, _) = compile_parameterized(statement);
+    let mut hasher = DefaultHasher::new();
+    sql.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub fn compile_statement(statement: &QueryBlock) -> String {
+    let nodes = ordered_nodes(statement);
+    let capacity = nodes.iter().map(|node| node.query_part.len() + 1).sum();
+    let mut compiled = String::with_capacity(capacity);
+    for node in nodes {
+        if !compiled.is_empty() {
+            compiled.push(' ');
+        }
+        compiled.push_str(&node.query_part);
+    }
+    compiled
+}
+
+// An UPDATE or DELETE without a WHERE clause touches every row, which is
+// almost always a mistake, so it is rejected unless explicitly allowed.
+pub fn validate(statement: &QueryBlock) -> Result<(), QueryError> {
+    let guarded = matches!(
+        statement.statement_type,
+        StatementType::Update | StatementType::Delete
+    );
+    if guarded && !statement.allow_full_scan && !statement.has_clause(ClauseKind::Where) {
+        return Err(QueryError::MissingWhereClause {
+            statement: statement.query_part.clone(),
+        });
+    }
+    Ok(())
+}
+
+// The statement head stays on the first line and every following clause
+// gets its own indented line, in the same order `compile_statement` uses.
+pub fn compile_statement_pretty(statement: &QueryBlock) -> String {
+    let nodes = ordered_nodes(statement);
+    let mut compiled = nodes[0].query_part.clone();
+    for node in &nodes[1..] {
+        compiled.push_str("\n  ");
+        compiled.push_str(&node.query_part);
+    }
+    compiled
+}
+
+// A raw fragment may already end the statement with a semicolon, in which
+// case no second one is added.
+pub fn compile_statement_terminated(statement: &QueryBlock) -> String {
+    let mut compiled = compile_statement(statement);
+    if !compiled.trim_end().ends_with(';') {
+        compiled.push(';');
+    }
+    compiled
+}
+
+// EXPLAIN ANALYZE actually runs the statement, including any writes.
+pub fn explain(statement: &QueryBlock, analyze: bool) -> String {
+    let keyword = if analyze {
+        "EXPLAIN ANALYZE"
+    } else {
+        "EXPLAIN"
+    };
+    format!("{} {}", keyword, compile_statement(statement))
+}
+
+pub fn compile_batch(statements: &[QueryBlock]) -> String {
+    statements
+        .iter()
+        .map(|statement| {
+            compile_statement(statement)
+                .trim_end()
+                .trim_end_matches(';')
+                .to_string()
+        })
+        .collect::<Vec<String>>()
+        .join(";\n")
+}
+
+// The head node always comes first; the stable sort keeps clauses of the
+// same kind, such as several joins, in the order they were added.
+fn ordered_nodes(statement: &QueryBlock) -> Vec<&QueryBlock> {
+    let mut nodes = Vec::new();
+    let mut node = Some(statement);
+    while let Some(current) = node {
+        nodes.push(current);
+        node = current.secondary_part.as_deref();
+    }
+    nodes[1..].sort_by_key(|node| node.clause_kind);
+    nodes
+}
+
+// Identifiers are stored in the AST with ANSI double quotes and are only
+// translated into the dialect's quoting here, at compile time. Values the
+// builder was given, booleans included, are spelled and escaped for the
+// dialect as they are inlined; raw SQL text is left as written.
+pub fn compile_statement_with(statement: &QueryBlock, dialect: Dialect) -> String {
+    let mut node = Some(statement);
+    while let Some(current) = node {
+        if let Some(required) = current.dialect {
+            if required != dialect {
+                panic!(
+                    "`{}` is only supported by {:?}, not {:?}",
+                    current.query_part, required, dialect
+                );
+            }
+        }
+        node = current.secondary_part.as_deref();
+    }
+    let sql = statement_part(statement).render(KeywordCase::Upper, |_, literal| {
+        literal.inline(Some(dialect))
+    });
+    let mut compiled = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    while let Some(current) = chars.next() {
+        match current {
+            '\'' => {
+                compiled.push(current);
+                for next in chars.by_ref() {
+                    compiled.push(next);
+                    if next == '\'' {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                let mut identifier = String::new();
+                while let Some(next) = chars.next() {
+                    if next == '"' {
+                        if chars.peek() != Some(&'"') {
+                            break;
+                        }
+                        chars.next();
+                    }
+                    identifier.push(next);
+                }
+                compiled.push_str(&dialect.quote_identifier(&identifier));
+            }
+            _ => compiled.push(current),
+        }
+    }
+    compiled
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeywordCase {
+    Upper,
+    Lower,
+}
+
+impl KeywordCase {
+    fn apply(&self, keyword: &str) -> String {
+        match self {
+            KeywordCase::Upper => keyword.to_uppercase(),
+            KeywordCase::Lower => keyword.to_lowercase(),
+        }
+    }
+}
+
+// Recases the keywords the builder emitted while generating the statement.
+// Raw SQL, such as `where_clause` text and the strings returned by helpers
+// like `count` or `cast`, is left exactly as written, and so are
+// identifiers, even ones spelled like a keyword.
+pub fn compile_statement_with_case(statement: &QueryBlock, case: KeywordCase) -> String {
+    statement_part(statement).render(case, |_, literal| match literal {
+        Literal::Bool(_) => case.apply(&literal.inline(None)),
+        _ => literal.inline(None),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model);
+        assert_eq!(query.query_part, "SELECT * FROM users");
+    }
+
+    #[test]
+    fn test_select_with_fields() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["id".to_string(), "name".to_string()]),
+            ..Default::default()
+        };
+        let query = select(&model);
+        assert_eq!(query.query_part, "SELECT id, name FROM users");
+    }
+
+    #[test]
+    fn test_insert_values() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = insert(&model).values(&vec!["1".to_string(), "John".to_string()]);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(compiled_query, "INSERT INTO users VALUES ('1', 'John')");
+    }
+
+    #[test]
+    fn test_select_where_clause() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).where_clause(&"id = 1".to_string());
+        let compiled_query = compile_statement(&query);
+        assert_eq!(compiled_query, "SELECT * FROM users WHERE id = 1");
+    }
+
+    #[test]
+    fn test_update_values() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["name".to_string()]),
+            ..Default::default()
+        };
+        let mut arguments = HashMap::new();
+        arguments.insert("name".to_string(), "John".to_string());
+        let query = update(&model).unwrap().set(&arguments);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(compiled_query, "UPDATE users SET name = 'John'");
+    }
+
+    #[test]
+    fn test_update_where_clause() {
+        let mut arguments = HashMap::new();
+        arguments.insert("id".to_string(), "2".to_string());
+        let where_string = "id = 1".to_string();
+        let fields = Some(vec!["id".to_string()]);
+        let model = Model {
+            name: "users".to_string(),
+            fields,
+            ..Default::default()
+        };
+        let query = update(&model)
+            .unwrap()
+            .set(&arguments)
+            .where_clause(&where_string);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(compiled_query, "UPDATE users SET id = '2' WHERE id = 1");
+    }
+
+    #[test]
+    fn test_select_order_by() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).order_by(&vec!["name".to_string(), "id".to_string()]);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(compiled_query, "SELECT * FROM users ORDER BY name, id");
+    }
+
+    #[test]
+    fn test_select_where_order_by() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model)
+            .where_clause(&"age > 18".to_string())
+            .order_by(&vec!["name".to_string()]);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "SELECT * FROM users WHERE age > 18 ORDER BY name"
+        );
+    }
+
+    #[test]
+    fn test_order_by_empty_columns() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).order_by(&vec![]);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(compiled_query, "SELECT * FROM users");
+    }
+
+    #[test]
+    fn test_order_by_with_mixed_directions() {
+        let model = Model {
+            name: "products".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).order_by_with(&vec![
+            ("price".to_string(), OrderDirection::Desc),
+            ("name".to_string(), OrderDirection::Asc),
+        ]);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "SELECT * FROM products ORDER BY price DESC, name ASC"
+        );
+    }
+
+    #[test]
+    fn test_order_by_with_single_descending() {
+        let model = Model {
+            name: "products".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query =
+            select(&model).order_by_with(&vec![("price".to_string(), OrderDirection::Desc)]);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(compiled_query, "SELECT * FROM products ORDER BY price DESC");
+    }
+
+    #[test]
+    fn test_order_by_with_nulls_placement() {
+        let model = Model {
+            name: "products".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).order_by_with_nulls(&vec![
+            (
+                "discount".to_string(),
+                OrderDirection::Desc,
+                Some(NullsPlacement::Last),
+            ),
+            ("name".to_string(), OrderDirection::Asc, None),
+        ]);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "SELECT * FROM products ORDER BY discount DESC NULLS LAST, name ASC"
+        );
+    }
+
+    #[test]
+    fn test_select_group_by() {
+        let model = Model {
+            name: "employees".to_string(),
+            fields: Some(vec!["dept".to_string(), "count(*)".to_string()]),
+            ..Default::default()
+        };
+        let query = select(&model)
+            .where_clause(&"active = true".to_string())
+            .group_by(&vec!["dept".to_string()]);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "SELECT dept, count(*) FROM employees WHERE active = true GROUP BY dept"
+        );
+    }
+
+    #[test]
+    fn test_group_by_empty_columns() {
+        let model = Model {
+            name: "employees".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).group_by(&vec![]);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(compiled_query, "SELECT * FROM employees");
+    }
+
+    #[test]
+    fn test_group_by_having() {
+        let model = Model {
+            name: "employees".to_string(),
+            fields: Some(vec!["dept".to_string(), "count(*)".to_string()]),
+            ..Default::default()
+        };
+        let query = select(&model)
+            .group_by(&vec!["dept".to_string()])
+            .having(&"count(*) > 5".to_string());
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "SELECT dept, count(*) FROM employees GROUP BY dept HAVING count(*) > 5"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "HAVING clause must follow a GROUP BY clause")]
+    fn test_having_without_group_by() {
+        let model = Model {
+            name: "employees".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        select(&model).having(&"count(*) > 5".to_string());
+    }
+
+    #[test]
+    fn test_select_limit_offset() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).limit(10).offset(20);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(compiled_query, "SELECT * FROM users LIMIT 10 OFFSET 20");
+    }
+
+    #[test]
+    fn test_select_offset_without_limit() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).offset(20);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(compiled_query, "SELECT * FROM users OFFSET 20");
+    }
+
+    #[test]
+    fn test_select_inner_join() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model)
+            .join(
+                &"orders".to_string(),
+                &"users.id = orders.user_id".to_string(),
+            )
+            .where_clause(&"orders.total > 100".to_string());
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "SELECT * FROM users INNER JOIN orders ON users.id = orders.user_id WHERE orders.total > 100"
+        );
+    }
+
+    #[test]
+    fn test_select_join_kinds() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let cases = vec![
+            (JoinKind::Inner, "INNER JOIN"),
+            (JoinKind::Left, "LEFT JOIN"),
+            (JoinKind::Right, "RIGHT JOIN"),
+            (JoinKind::Full, "FULL OUTER JOIN"),
+        ];
+        for (kind, keyword) in cases {
+            let query = select(&model).join_kind(
+                kind,
+                &"orders".to_string(),
+                &"users.id = orders.user_id".to_string(),
+            );
+            let compiled_query = compile_statement(&query);
+            assert_eq!(
+                compiled_query,
+                format!(
+                    "SELECT * FROM users {} orders ON users.id = orders.user_id",
+                    keyword
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_select_cross_join() {
+        let model = Model {
+            name: "a".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).cross_join(&"b".to_string());
+        let compiled_query = compile_statement(&query);
+        assert_eq!(compiled_query, "SELECT * FROM a CROSS JOIN b");
+    }
+
+    #[test]
+    fn test_select_distinct() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select_distinct(&model);
+        assert_eq!(query.query_part, "SELECT DISTINCT * FROM users");
+    }
+
+    #[test]
+    fn test_select_distinct_with_fields() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["city".to_string(), "country".to_string()]),
+            ..Default::default()
+        };
+        let query = select_distinct(&model).order_by(&vec!["city".to_string()]);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "SELECT DISTINCT city, country FROM users ORDER BY city"
+        );
+    }
+
+    #[test]
+    fn test_union() {
+        let users = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["email".to_string()]),
+            ..Default::default()
+        };
+        let admins = Model {
+            name: "admins".to_string(),
+            fields: Some(vec!["email".to_string()]),
+            ..Default::default()
+        };
+        let query = union(select(&users), select(&admins), false);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "SELECT email FROM users UNION SELECT email FROM admins"
+        );
+    }
+
+    #[test]
+    fn test_union_all() {
+        let users = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["email".to_string()]),
+            ..Default::default()
+        };
+        let admins = Model {
+            name: "admins".to_string(),
+            fields: Some(vec!["email".to_string()]),
+            ..Default::default()
+        };
+        let query = union(select(&users), select(&admins), true);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "SELECT email FROM users UNION ALL SELECT email FROM admins"
+        );
+    }
+
+    #[test]
+    fn test_intersect_with_where_clause() {
+        let users = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["email".to_string()]),
+            ..Default::default()
+        };
+        let admins = Model {
+            name: "admins".to_string(),
+            fields: Some(vec!["email".to_string()]),
+            ..Default::default()
+        };
+        let query = intersect(
+            select(&users).where_clause(&"active = true".to_string()),
+            select(&admins),
+        );
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "SELECT email FROM users WHERE active = true INTERSECT SELECT email FROM admins"
+        );
+    }
+
+    #[test]
+    fn test_except_with_where_clause() {
+        let users = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["email".to_string()]),
+            ..Default::default()
+        };
+        let banned = Model {
+            name: "banned".to_string(),
+            fields: Some(vec!["email".to_string()]),
+            ..Default::default()
+        };
+        let query = except(
+            select(&users),
+            select(&banned).where_clause(&"permanent = true".to_string()),
+        );
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "SELECT email FROM users EXCEPT SELECT email FROM banned WHERE permanent = true"
+        );
+    }
+
+    #[test]
+    fn test_where_in_nested_subquery() {
+        let users = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let orders = Model {
+            name: "orders".to_string(),
+            fields: Some(vec!["user_id".to_string()]),
+            ..Default::default()
+        };
+        let products = Model {
+            name: "products".to_string(),
+            fields: Some(vec!["id".to_string()]),
+            ..Default::default()
+        };
+        let inner = select(&products).where_clause(&"price > 100".to_string());
+        let middle = select(&orders).where_in_subquery(&"product_id".to_string(), inner);
+        let query = select(&users).where_in_subquery(&"id".to_string(), middle);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "SELECT * FROM users WHERE id IN (SELECT user_id FROM orders WHERE product_id IN (SELECT id FROM products WHERE price > 100))"
+        );
+    }
+
+    #[test]
+    fn test_with_cte() {
+        let orders = Model {
+            name: "orders".to_string(),
+            fields: Some(vec!["user_id".to_string(), "total".to_string()]),
+            ..Default::default()
+        };
+        let big_orders = Model {
+            name: "big_orders".to_string(),
+            fields: Some(vec!["user_id".to_string()]),
+            ..Default::default()
+        };
+        let body = select(&orders).where_clause(&"total > 100".to_string());
+        let main = select(&big_orders);
+        let query = with(&"big_orders".to_string(), body, main);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "WITH big_orders AS (SELECT user_id, total FROM orders WHERE total > 100) SELECT user_id FROM big_orders"
+        );
+    }
+
+    #[test]
+    fn test_with_cte_keeps_main_clauses() {
+        let cte = Model {
+            name: "c".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = with(
+            &"c".to_string(),
+            select(&cte),
+            select(&cte).where_clause(&"x = 1".to_string()),
+        )
+        .where_clause(&"y = 2".to_string())
+        .limit(5);
+        assert_eq!(
+            compile_statement(&query),
+            "WITH c AS (SELECT * FROM c) SELECT * FROM c WHERE x = 1 AND y = 2 LIMIT 5"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicate LIMIT clause")]
+    fn test_with_cte_duplicate_clause() {
+        let cte = Model {
+            name: "c".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        with(&"c".to_string(), select(&cte), select(&cte).limit(1)).limit(2);
+    }
+
+    #[test]
+    fn test_insert_returning() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = insert(&model)
+            .values(&vec!["1".to_string()])
+            .returning(&vec!["id".to_string()]);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "INSERT INTO users VALUES ('1') RETURNING id"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "RETURNING clause is only valid for INSERT, UPDATE and DELETE")]
+    fn test_select_returning() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        select(&model).returning(&vec!["id".to_string()]);
+    }
+
+    #[test]
+    fn test_insert_on_conflict_do_update() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["id".to_string(), "name".to_string()]),
+            ..Default::default()
+        };
+        let mut updates = HashMap::new();
+        updates.insert("name".to_string(), "EXCLUDED.name".to_string());
+        let query = insert(&model)
+            .values(&vec!["1".to_string(), "John".to_string()])
+            .on_conflict(&vec!["id".to_string()], &updates);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "INSERT INTO users (id, name) VALUES ('1', 'John') ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name"
+        );
+    }
+
+    #[test]
+    fn test_insert_on_conflict_do_nothing() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["id".to_string(), "name".to_string()]),
+            ..Default::default()
+        };
+        let query = insert(&model)
+            .values(&vec!["1".to_string(), "John".to_string()])
+            .on_conflict(&vec!["id".to_string()], &HashMap::new());
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "INSERT INTO users (id, name) VALUES ('1', 'John') ON CONFLICT (id) DO NOTHING"
+        );
+    }
+
+    #[test]
+    fn test_insert_on_conflict_without_target() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["id".to_string()]),
+            ..Default::default()
+        };
+        let query = insert(&model)
+            .values(&vec!["1".to_string()])
+            .on_conflict(&vec![], &HashMap::new());
+        assert_eq!(
+            compile_statement(&query),
+            "INSERT INTO users (id) VALUES ('1') ON CONFLICT DO NOTHING"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ON CONFLICT DO UPDATE needs a conflict target")]
+    fn test_insert_on_conflict_update_without_target() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["id".to_string(), "name".to_string()]),
+            ..Default::default()
+        };
+        let mut updates = HashMap::new();
+        updates.insert("name".to_string(), "EXCLUDED.name".to_string());
+        insert(&model)
+            .values(&vec!["1".to_string(), "John".to_string()])
+            .on_conflict(&vec![], &updates);
+    }
+
+    #[test]
+    fn test_count_star() {
+        assert_eq!(count("*"), "COUNT(*)");
+    }
+
+    #[test]
+    fn test_select_aggregates() {
+        let model = Model {
+            name: "orders".to_string(),
+            fields: Some(vec![
+                count("*"),
+                sum("total"),
+                avg("total"),
+                min("total"),
+                max("total"),
+            ]),
+            ..Default::default()
+        };
+        let query = select(&model);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "SELECT COUNT(*), SUM(total), AVG(total), MIN(total), MAX(total) FROM orders"
+        );
+    }
+
+    #[test]
+    fn test_select_column_alias() {
+        let model = Model {
+            name: "orders".to_string(),
+            fields: Some(vec![alias(&count("*"), "total"), alias("price", "cost")]),
+            ..Default::default()
+        };
+        let query = select(&model);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "SELECT COUNT(*) AS total, price AS cost FROM orders"
+        );
+    }
+
+    #[test]
+    fn test_aliased_model() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["u.id".to_string()]),
+            alias: Some("u".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(select(&model).query_part, "SELECT u.id FROM users AS u");
+        assert_eq!(
+            select_distinct(&model).query_part,
+            "SELECT DISTINCT u.id FROM users AS u"
+        );
+        assert_eq!(update(&model).unwrap().query_part, "UPDATE users AS u");
+        assert_eq!(delete(&model).query_part, "DELETE FROM users AS u");
+        assert_eq!(insert(&model).query_part, "INSERT INTO users AS u (u.id)");
+    }
+
+    #[test]
+    fn test_unaliased_model() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["id".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(select(&model).query_part, "SELECT id FROM users");
+        assert_eq!(update(&model).unwrap().query_part, "UPDATE users");
+        assert_eq!(delete(&model).query_part, "DELETE FROM users");
+        assert_eq!(insert(&model).query_part, "INSERT INTO users (id)");
+    }
+
+    #[test]
+    fn test_self_join_with_alias() {
+        let model = Model {
+            name: "employees".to_string(),
+            fields: Some(vec!["e.name".to_string(), "m.name".to_string()]),
+            alias: Some("e".to_string()),
+            ..Default::default()
+        };
+        let query = select(&model).join_kind(
+            JoinKind::Left,
+            &"employees AS m".to_string(),
+            &"e.manager_id = m.id".to_string(),
+        );
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "SELECT e.name, m.name FROM employees AS e LEFT JOIN employees AS m ON e.manager_id = m.id"
+        );
+    }
+
+    #[test]
+    fn test_compile_parameterized_insert() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["id".to_string(), "name".to_string()]),
+            ..Default::default()
+        };
+        let query = insert(&model).values(&vec!["1".to_string(), "John".to_string()]);
+        let (sql, parameters) = compile_parameterized(&query);
+        assert_eq!(sql, "INSERT INTO users (id, name) VALUES ($1, $2)");
+        assert_eq!(parameters, vec!["1".to_string(), "John".to_string()]);
+    }
+
+    #[test]
+    fn test_compile_parameterized_update_where() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["name".to_string()]),
+            ..Default::default()
+        };
+        let mut arguments = HashMap::new();
+        arguments.insert("name".to_string(), "John".to_string());
+        let query = update(&model)
+            .unwrap()
+            .set(&arguments)
+            .where_eq(&"id".to_string(), &Value::Int(1))
+            .where_cond(Condition::Eq("nickname".to_string(), "O'Brien".to_string()));
+        let (sql, parameters) = compile_parameterized(&query);
+        assert_eq!(
+            sql,
+            "UPDATE users SET name = $1 WHERE id = $2 AND nickname = $3"
+        );
+        assert_eq!(
+            parameters,
+            vec!["John".to_string(), "1".to_string(), "O'Brien".to_string()]
+        );
+        assert_eq!(
+            compile_statement(&query),
+            "UPDATE users SET name = 'John' WHERE id = 1 AND nickname = 'O''Brien'"
+        );
+    }
+
+    #[test]
+    fn test_where_clause_is_verbatim() {
+        let model = Model {
+            name: "t1".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model)
+            .where_clause(&"t1.col2 > 10 AND \"weird 3\" = 4.5".to_string())
+            .limit(5);
+        let (sql, parameters) = compile_parameterized(&query);
+        assert_eq!(
+            sql,
+            "SELECT * FROM t1 WHERE t1.col2 > 10 AND \"weird 3\" = 4.5 LIMIT 5"
+        );
+        assert!(parameters.is_empty());
+    }
+
+    #[test]
+    fn test_where_clause_keeps_typed_literals() {
+        let model = Model {
+            name: "orders".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let conditions = [
+            "created_at > now() - interval '7 days'",
+            "placed_on = DATE '2024-01-01'",
+            "note = E'a\\nb'",
+            "price::numeric(10,2) > 5",
+            "x = 1e3",
+        ];
+        for condition in conditions {
+            let query = select(&model).where_clause(&condition.to_string());
+            let (sql, parameters) = compile_parameterized(&query);
+            assert_eq!(sql, format!("SELECT * FROM orders WHERE {}", condition));
+            assert!(parameters.is_empty());
+        }
+        let grouped = select(&model)
+            .where_exists(select(&model).group_by(&vec!["1".to_string()]))
+            .where_eq(&"id".to_string(), &Value::Int(7));
+        assert_eq!(
+            compile_parameterized(&grouped),
+            (
+                "SELECT * FROM orders WHERE EXISTS (SELECT * FROM orders GROUP BY 1) AND id = $1"
+                    .to_string(),
+                vec!["7".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn test_escape_identifier() {
+        assert_eq!(escape_identifier("users"), "\"users\"");
+        assert_eq!(escape_identifier("we\"ird"), "\"we\"\"ird\"");
+        assert_eq!(escape_identifier("public.users"), "\"public.users\"");
+        assert_eq!(
+            escape_identifier("users\"; DROP TABLE users; --"),
+            "\"users\"\"; DROP TABLE users; --\""
+        );
+    }
+
+    #[test]
+    fn test_quoted_identifiers() {
+        let model = Model {
+            name: "user\"s".to_string(),
+            fields: Some(vec!["id".to_string(), "first.name".to_string()]),
+            schema: None,
+            primary_key: None,
+            alias: Some("u".to_string()),
+            quote_identifiers: true,
+        };
+        assert_eq!(
+            select(&model).query_part,
+            "SELECT \"id\", \"first.name\" FROM \"user\"\"s\" AS \"u\""
+        );
+        assert_eq!(
+            update(&model).unwrap().query_part,
+            "UPDATE \"user\"\"s\" AS \"u\""
+        );
+        assert_eq!(
+            delete(&model).query_part,
+            "DELETE FROM \"user\"\"s\" AS \"u\""
+        );
+        assert_eq!(
+            insert(&model).query_part,
+            "INSERT INTO \"user\"\"s\" AS \"u\" (\"id\", \"first.name\")"
+        );
+    }
+
+    #[test]
+    fn test_escape_literal_apostrophe() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["name".to_string()]),
+            ..Default::default()
+        };
+        let query = insert(&model).values(&vec!["O'Brien".to_string()]);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "INSERT INTO users (name) VALUES ('O''Brien')"
+        );
+    }
+
+    #[test]
+    fn test_escape_literal_newline() {
+        let model = Model {
+            name: "notes".to_string(),
+            fields: Some(vec!["body".to_string()]),
+            ..Default::default()
+        };
+        let mut arguments = HashMap::new();
+        arguments.insert("body".to_string(), "first\nsecond".to_string());
+        let query = update(&model).unwrap().set(&arguments);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(compiled_query, "UPDATE notes SET body = 'first\nsecond'");
+    }
+
+    #[test]
+    fn test_values_numeric_hint() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec![
+                "id".to_string(),
+                "name".to_string(),
+                "score".to_string(),
+                "rank".to_string(),
+            ]),
+            ..Default::default()
+        };
+        let query = insert(&model).values_hinted(&vec![
+            ("42".to_string(), LiteralHint::Numeric),
+            ("1; DROP TABLE users".to_string(), LiteralHint::Numeric),
+            ("-1.5".to_string(), LiteralHint::Numeric),
+            ("inf".to_string(), LiteralHint::Numeric),
+        ]);
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "INSERT INTO users (id, name, score, rank) VALUES (42, '1; DROP TABLE users', -1.5, 'inf')"
+        );
+    }
+
+    #[test]
+    fn test_compile_non_ascii_table_name() {
+        let model = Model {
+            name: "ügyfelek".to_string(),
+            fields: Some(vec!["név".to_string()]),
+            ..Default::default()
+        };
+        let query = select(&model).where_clause(&"név = 'Ádám'".to_string());
+        let compiled_query = compile_statement(&query);
+        assert_eq!(
+            compiled_query,
+            "SELECT név FROM ügyfelek WHERE név = 'Ádám'"
+        );
+
+        let leading = QueryBlock::new("ÉRTÉK".to_string(), StatementType::Select);
+        assert_eq!(compile_statement(&leading), "ÉRTÉK");
+    }
+
+    #[test]
+    fn test_update_without_fields() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let error = update(&model).err().unwrap();
+        assert_eq!(
+            error,
+            QueryError::MissingFields {
+                model: "users".to_string()
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "update query on `users` must have fields"
+        );
+    }
+
+    #[test]
+    fn test_insert_values_many() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["id".to_string(), "name".to_string()]),
+            ..Default::default()
+        };
+        let query = insert(&model).values_many(&vec![
+            vec!["1".to_string(), "a".to_string()],
+            vec!["2".to_string(), "b".to_string()],
+            vec!["3".to_string(), "c".to_string()],
+        ]);
+        assert_eq!(
+            compile_statement(&query),
+            "INSERT INTO users (id, name) VALUES ('1', 'a'), ('2', 'b'), ('3', 'c')"
+        );
+        let (sql, parameters) = compile_parameterized(&query);
+        assert_eq!(
+            sql,
+            "INSERT INTO users (id, name) VALUES ($1, $2), ($3, $4), ($5, $6)"
+        );
+        assert_eq!(parameters.len(), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "VALUES clause must have at least one row")]
+    fn test_insert_values_many_empty() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        insert(&model).values_many(&vec![]);
+    }
+
+    #[test]
+    fn test_condition_and_with_nested_or() {
+        let cond = Condition::And(
+            Box::new(Condition::Eq("active".to_string(), "yes".to_string())),
+            Box::new(Condition::Or(
+                Box::new(Condition::Gt("age".to_string(), "18".to_string())),
+                Box::new(Condition::Lt("score".to_string(), "5".to_string())),
+            )),
+        );
+        assert_eq!(
+            cond.to_sql(),
+            "active = 'yes' AND (age > '18' OR score < '5')"
+        );
+    }
+
+    #[test]
+    fn test_condition_or_with_nested_and() {
+        let cond = Condition::Or(
+            Box::new(Condition::And(
+                Box::new(Condition::Eq("a".to_string(), "1".to_string())),
+                Box::new(Condition::Eq("b".to_string(), "2".to_string())),
+            )),
+            Box::new(Condition::Eq("c".to_string(), "3".to_string())),
+        );
+        assert_eq!(cond.to_sql(), "a = '1' AND b = '2' OR c = '3'");
+    }
+
+    #[test]
+    fn test_select_where_cond() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let cond = Condition::And(
+            Box::new(Condition::Or(
+                Box::new(Condition::Eq("role".to_string(), "admin".to_string())),
+                Box::new(Condition::Eq("role".to_string(), "owner".to_string())),
+            )),
+            Box::new(Condition::Eq("name".to_string(), "O'Brien".to_string())),
+        );
+        let query = select(&model).where_cond(cond);
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE (role = 'admin' OR role = 'owner') AND name = 'O''Brien'"
+        );
+        let (sql, parameters) = compile_parameterized(&query);
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE (role = $1 OR role = $2) AND name = $3"
+        );
+        assert_eq!(parameters[2], "O'Brien");
+    }
+
+    #[test]
+    fn test_where_in() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).where_in(
+            &"id".to_string(),
+            &vec!["1".to_string(), "2".to_string(), "3".to_string()],
+        );
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE id IN ('1', '2', '3')"
+        );
+    }
+
+    #[test]
+    fn test_where_in_empty_matches_nothing() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).where_in(&"id".to_string(), &vec![]);
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE id IN (NULL)"
+        );
+    }
+
+    #[test]
+    fn test_where_between_after_join() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model)
+            .join(
+                &"profiles".to_string(),
+                &"users.id = profiles.user_id".to_string(),
+            )
+            .where_between(
+                &"profiles.age".to_string(),
+                &"18".to_string(),
+                &"65".to_string(),
+            );
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users INNER JOIN profiles ON users.id = profiles.user_id WHERE profiles.age BETWEEN 18 AND 65"
+        );
+    }
+
+    #[test]
+    fn test_where_like() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).where_like(&"name".to_string(), &"%john%".to_string());
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE name LIKE '%john%'"
+        );
+    }
+
+    #[test]
+    fn test_where_ilike_with_quote() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).where_ilike(&"name".to_string(), &"%o'brien%".to_string());
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE name ILIKE '%o''brien%'"
+        );
+        let (sql, parameters) = compile_parameterized(&query);
+        assert_eq!(sql, "SELECT * FROM users WHERE name ILIKE $1");
+        assert_eq!(parameters, vec!["%o'brien%".to_string()]);
+    }
+
+    #[test]
+    fn test_where_null() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).where_null(&"deleted_at".to_string());
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE deleted_at IS NULL"
+        );
+    }
+
+    #[test]
+    fn test_where_not_null() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).where_not_null(&"address_2".to_string());
+        let (sql, parameters) = compile_parameterized(&query);
+        assert_eq!(sql, "SELECT * FROM users WHERE address_2 IS NOT NULL");
+        assert!(parameters.is_empty());
+    }
+
+    #[test]
+    fn test_compile_statement_with_dialects() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["id".to_string(), "we\"ird`name".to_string()]),
+            quote_identifiers: true,
+            ..Default::default()
+        };
+        let query = select(&model).where_clause(&"\"id\" = 'it''s \"quoted\"'".to_string());
+        assert_eq!(
+            compile_statement_with(&query, Dialect::Postgres),
+            "SELECT \"id\", \"we\"\"ird`name\" FROM \"users\" WHERE \"id\" = 'it''s \"quoted\"'"
+        );
+        assert_eq!(
+            compile_statement_with(&query, Dialect::Sqlite),
+            compile_statement_with(&query, Dialect::Postgres)
+        );
+        assert_eq!(
+            compile_statement_with(&query, Dialect::MySQL),
+            "SELECT `id`, `we\"ird``name` FROM `users` WHERE `id` = 'it''s \"quoted\"'"
+        );
+    }
+
+    #[test]
+    fn test_mysql_escapes_backslashes() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["name".to_string()]),
+            ..Default::default()
+        };
+        let query = insert(&model).values(&vec!["\\' OR 1=1 -- ".to_string()]);
+        assert_eq!(
+            compile_statement_with(&query, Dialect::MySQL),
+            "INSERT INTO users (name) VALUES ('\\\\'' OR 1=1 -- ')"
+        );
+        assert_eq!(
+            compile_statement_with(&query, Dialect::Postgres),
+            "INSERT INTO users (name) VALUES ('\\'' OR 1=1 -- ')"
+        );
+        let query = select(&model).where_eq(&"name".to_string(), &Value::Text("a\\b".to_string()));
+        assert_eq!(
+            compile_statement_with(&query, Dialect::MySQL),
+            "SELECT name FROM users WHERE name = 'a\\\\b'"
+        );
+    }
+
+    #[test]
+    fn test_display_query_block() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        assert_eq!(format!("{}", select(&model)), "SELECT * FROM users");
+        let query = select(&model).where_clause(&"id = 1".to_string()).limit(1);
+        assert_eq!(format!("{}", query), compile_statement(&query));
+    }
+
+    #[test]
+    fn test_column_rendering() {
+        assert_eq!(Column::new("id").qualified(), "id");
+        assert_eq!(Column::of("users", "id").qualified(), "users.id");
+    }
+
+    #[test]
+    fn test_select_with_qualified_columns() {
+        let model = Model::with_columns(
+            "users",
+            vec![
+                Column::of("users", "id"),
+                Column::of("orders", "total"),
+                Column::new("status"),
+            ],
+        );
+        let query = select(&model).join(
+            &"orders".to_string(),
+            &"users.id = orders.user_id".to_string(),
+        );
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT users.id, orders.total, status FROM users INNER JOIN orders ON users.id = orders.user_id"
+        );
+    }
+
+    #[test]
+    fn test_out_of_order_clauses_compile_canonically() {
+        let model = Model {
+            name: "orders".to_string(),
+            fields: Some(vec!["customer_id".to_string(), count("*")]),
+            ..Default::default()
+        };
+        let query = select(&model)
+            .limit(10)
+            .order_by(&vec!["customer_id".to_string()])
+            .group_by(&vec!["customer_id".to_string()])
+            .having(&"COUNT(*) > 1".to_string())
+            .where_clause(&"total > 100".to_string())
+            .join(
+                &"customers".to_string(),
+                &"customers.id = orders.customer_id".to_string(),
+            );
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT customer_id, COUNT(*) FROM orders INNER JOIN customers ON customers.id = orders.customer_id WHERE total > 100 GROUP BY customer_id HAVING COUNT(*) > 1 ORDER BY customer_id LIMIT 10"
+        );
+        let (sql, parameters) = compile_parameterized(&query);
+        assert!(sql.contains("WHERE total > 100 GROUP BY"));
+        assert!(parameters.is_empty());
+    }
+
+    #[test]
+    fn test_out_of_order_update_clauses() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["name".to_string()]),
+            ..Default::default()
+        };
+        let mut arguments = HashMap::new();
+        arguments.insert("name".to_string(), "John".to_string());
+        let query = update(&model)
+            .unwrap()
+            .returning(&vec!["id".to_string()])
+            .where_eq(&"id".to_string(), &Value::Int(1))
+            .set(&arguments);
+        let (sql, parameters) = compile_parameterized(&query);
+        assert_eq!(sql, "UPDATE users SET name = $1 WHERE id = $2 RETURNING id");
+        assert_eq!(parameters, vec!["John".to_string(), "1".to_string()]);
+    }
+
+    #[test]
+    fn test_where_clauses_are_merged() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model)
+            .where_clause(&"age > 18".to_string())
+            .where_eq(&"name".to_string(), &Value::Text("John".to_string()))
+            .where_null(&"deleted_at".to_string());
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE age > 18 AND name = 'John' AND deleted_at IS NULL"
+        );
+        let (sql, parameters) = compile_parameterized(&query);
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE age > 18 AND name = $1 AND deleted_at IS NULL"
+        );
+        assert_eq!(parameters, vec!["John".to_string()]);
+    }
+
+    #[test]
+    fn test_merged_where_keeps_or_precedence() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model)
+            .where_clause(&"role = 'admin' OR role = 'owner'".to_string())
+            .where_clause(&"active = true".to_string())
+            .where_clause(&"(a OR b) AND c".to_string());
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE (role = 'admin' OR role = 'owner') AND active = true AND (a OR b) AND c"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicate LIMIT clause")]
+    fn test_duplicate_limit() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        select(&model).limit(10).limit(20);
+    }
+
+    #[test]
+    fn test_compile_long_chain() {
+        let model = Model {
+            name: "t".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let mut query = select(&model);
+        for index in 0..10_000 {
+            query = query.cross_join(&format!("t{}", index));
+        }
+        let compiled_query = compile_statement(&query);
+        assert!(compiled_query.starts_with("SELECT * FROM t CROSS JOIN t0 CROSS JOIN t1 "));
+        assert!(compiled_query.ends_with(" CROSS JOIN t9999"));
+    }
+
+    #[test]
+    fn test_query_builder_matches_query_block() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let built = QueryBuilder::new(select(&model).where_clause(&"age > 18".to_string()))
+            .join(
+                &"orders".to_string(),
+                &"users.id = orders.user_id".to_string(),
+            )
+            .where_clause(&"orders.total > 100".to_string())
+            .limit(5)
+            .build();
+        let chained = select(&model)
+            .where_clause(&"age > 18".to_string())
+            .join(
+                &"orders".to_string(),
+                &"users.id = orders.user_id".to_string(),
+            )
+            .where_clause(&"orders.total > 100".to_string())
+            .limit(5);
+        assert_eq!(compile_statement(&built), compile_statement(&chained));
+        assert_eq!(
+            compile_parameterized(&built),
+            compile_parameterized(&chained)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicate LIMIT clause")]
+    fn test_query_builder_rejects_duplicates() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        QueryBuilder::new(select(&model).limit(1)).limit(2);
+    }
+
+    #[test]
+    fn test_query_builder_long_chain() {
+        let model = Model {
+            name: "t".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let mut builder = QueryBuilder::new(select(&model));
+        for index in 0..100_000 {
+            builder = builder.cross_join(&format!("t{}", index));
+        }
+        let query = builder.build();
+        let compiled_query = compile_statement(&query);
+        assert_eq!(compiled_query.matches("CROSS JOIN").count(), 100_000);
+        assert!(compiled_query.ends_with(" CROSS JOIN t99999"));
+    }
+
+    #[test]
+    fn test_where_exists_correlated() {
+        let users = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let orders = Model {
+            name: "orders".to_string(),
+            fields: Some(vec!["1".to_string()]),
+            ..Default::default()
+        };
+        let subquery = select(&orders)
+            .where_clause(&"orders.user_id = users.id".to_string())
+            .where_clause(&"orders.total > 100".to_string());
+        let query = select(&users).where_exists(subquery);
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE EXISTS (SELECT 1 FROM orders WHERE orders.user_id = users.id AND orders.total > 100)"
+        );
+    }
+
+    #[test]
+    fn test_where_not_exists() {
+        let users = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let bans = Model {
+            name: "bans".to_string(),
+            fields: Some(vec!["1".to_string()]),
+            ..Default::default()
+        };
+        let subquery = select(&bans).where_clause(&"bans.user_id = users.id".to_string());
+        let query = select(&users)
+            .where_clause(&"active = true".to_string())
+            .where_not_exists(subquery);
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE active = true AND NOT EXISTS (SELECT 1 FROM bans WHERE bans.user_id = users.id)"
+        );
+    }
+
+    #[test]
+    fn test_insert_select() {
+        let archive = Model {
+            name: "archived_users".to_string(),
+            fields: Some(vec!["id".to_string(), "name".to_string()]),
+            ..Default::default()
+        };
+        let users = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["id".to_string(), "name".to_string()]),
+            ..Default::default()
+        };
+        let source = select(&users).where_clause(&"deleted = true".to_string());
+        let query = insert_select(&archive, source);
+        assert_eq!(
+            compile_statement(&query),
+            "INSERT INTO archived_users (id, name) SELECT id, name FROM users WHERE deleted = true"
+        );
+    }
+
+    #[test]
+    fn test_nested_queries_keep_parameters() {
+        let users = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["id".to_string(), "name".to_string()]),
+            ..Default::default()
+        };
+        let by_name =
+            || select(&users).where_eq(&"name".to_string(), &Value::Text("bob".to_string()));
+        let bob = vec!["bob".to_string()];
+
+        let query = insert_select(&users, by_name());
+        assert_eq!(
+            compile_parameterized(&query),
+            (
+                "INSERT INTO users (id, name) SELECT id, name FROM users WHERE name = $1"
+                    .to_string(),
+                bob.clone()
+            )
+        );
+
+        let query = select_from_subquery(by_name(), "sub", None)
+            .where_eq(&"id".to_string(), &Value::Int(3));
+        assert_eq!(
+            compile_parameterized(&query),
+            (
+                "SELECT * FROM (SELECT id, name FROM users WHERE name = $1) AS sub WHERE id = $2"
+                    .to_string(),
+                vec!["bob".to_string(), "3".to_string()]
+            )
+        );
+
+        let query = count_query(&by_name().limit(10));
+        assert_eq!(
+            compile_parameterized(&query),
+            (
+                "SELECT COUNT(*) FROM (SELECT id, name FROM users WHERE name = $1) AS sub"
+                    .to_string(),
+                bob.clone()
+            )
+        );
+
+        let query = select(&users)
+            .where_eq(&"id".to_string(), &Value::Int(1))
+            .where_in_subquery(&"id".to_string(), by_name())
+            .where_exists(by_name());
+        let (sql, parameters) = compile_parameterized(&query);
+        assert_eq!(
+            sql,
+            "SELECT id, name FROM users WHERE id = $1 AND id IN (SELECT id, name FROM users WHERE name = $2) AND EXISTS (SELECT id, name FROM users WHERE name = $3)"
+        );
+        assert_eq!(parameters, vec!["1", "bob", "bob"]);
+    }
+
+    #[test]
+    fn test_insert_select_without_fields() {
+        let archive = Model {
+            name: "archived_users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let users = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = insert_select(&archive, select(&users));
+        assert_eq!(
+            compile_statement(&query),
+            "INSERT INTO archived_users SELECT * FROM users"
+        );
+    }
+
+    #[test]
+    fn test_update_from() {
+        let model = Model {
+            name: "a".to_string(),
+            fields: Some(vec!["x".to_string()]),
+            ..Default::default()
+        };
+        let mut arguments = HashMap::new();
+        arguments.insert("x".to_string(), "b.y".to_string());
+        let query = update(&model)
+            .unwrap()
+            .where_clause(&"a.id = b.id".to_string())
+            .from(&"b".to_string())
+            .set_raw(&arguments);
+        assert_eq!(
+            compile_statement(&query),
+            "UPDATE a SET x = b.y FROM b WHERE a.id = b.id"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "FROM clause can only be added to UPDATE statements")]
+    fn test_from_on_select() {
+        let model = Model {
+            name: "a".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        select(&model).from(&"b".to_string());
+    }
+
+    #[test]
+    fn test_delete_using() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = delete(&model)
+            .where_clause(&"users.id = banned.id".to_string())
+            .using(&"banned".to_string());
+        assert_eq!(
+            compile_statement(&query),
+            "DELETE FROM users USING banned WHERE users.id = banned.id"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "USING clause can only be added to DELETE statements")]
+    fn test_using_on_update() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["name".to_string()]),
+            ..Default::default()
+        };
+        update(&model).unwrap().using(&"banned".to_string());
+    }
+
+    #[test]
+    fn test_truncate() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        assert_eq!(compile_statement(&truncate(&model)), "TRUNCATE TABLE users");
+        assert_eq!(
+            compile_statement(&truncate_cascade(&model)),
+            "TRUNCATE TABLE users CASCADE"
+        );
+    }
+
+    #[test]
+    fn test_schema_qualified_select() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            schema: Some("public".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            compile_statement(&select(&model)),
+            "SELECT * FROM public.users"
+        );
+    }
+
+    #[test]
+    fn test_schema_qualified_quoted_insert() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["name".to_string()]),
+            schema: Some("public".to_string()),
+            quote_identifiers: true,
+            ..Default::default()
+        };
+        let query = insert(&model).values(&vec!["bob".to_string()]);
+        assert_eq!(
+            compile_statement(&query),
+            "INSERT INTO \"public\".\"users\" (\"name\") VALUES ('bob')"
+        );
+    }
+
+    #[test]
+    fn test_window() {
+        assert_eq!(
+            window(
+                "ROW_NUMBER()",
+                &vec!["dept".to_string()],
+                &vec!["salary".to_string()]
+            ),
+            "ROW_NUMBER() OVER (PARTITION BY dept ORDER BY salary)"
+        );
+        assert_eq!(
+            window("SUM(total)", &vec![], &vec!["created_at".to_string()]),
+            "SUM(total) OVER (ORDER BY created_at)"
+        );
+        assert_eq!(window("COUNT(*)", &vec![], &vec![]), "COUNT(*) OVER ()");
+    }
+
+    #[test]
+    fn test_case_expr() {
+        let case = CaseExpr::new()
+            .when("total > 100", "'large'")
+            .when("total > 10", "'medium'")
+            .else_("'small'");
+        assert_eq!(
+            case.to_sql(),
+            "CASE WHEN total > 100 THEN 'large' WHEN total > 10 THEN 'medium' ELSE 'small' END"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "CASE expression must have at least one WHEN arm")]
+    fn test_case_expr_without_arms() {
+        CaseExpr::new().else_("0").to_sql();
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let args = vec!["nickname".to_string(), "name".to_string(), "0".to_string()];
+        assert_eq!(coalesce(&args), "COALESCE(nickname, name, 0)");
+    }
+
+    #[test]
+    #[should_panic(expected = "COALESCE requires at least two arguments")]
+    fn test_coalesce_single_argument() {
+        coalesce(&vec!["name".to_string()]);
+    }
+
+    #[test]
+    fn test_nullif() {
+        assert_eq!(nullif("total", "0"), "NULLIF(total, 0)");
+    }
+
+    #[test]
+    fn test_cast_in_select() {
+        assert_eq!(cast("price", "NUMERIC"), "CAST(price AS NUMERIC)");
+        let model = Model {
+            name: "products".to_string(),
+            fields: Some(vec![
+                "id".to_string(),
+                alias(&cast("price", "NUMERIC(10, 2)"), "price"),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            compile_statement(&select(&model)),
+            "SELECT id, CAST(price AS NUMERIC(10, 2)) AS price FROM products"
+        );
+    }
+
+    #[test]
+    fn test_select_distinct_on() {
+        let model = Model {
+            name: "events".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select_distinct_on(&model, &vec!["user_id".to_string()])
+            .order_by(&vec!["user_id".to_string(), "created_at".to_string()]);
+        assert_eq!(
+            compile_statement_with(&query, Dialect::Postgres),
+            "SELECT DISTINCT ON (user_id) * FROM events ORDER BY user_id, created_at"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "is only supported by Postgres, not MySQL")]
+    fn test_select_distinct_on_other_dialect() {
+        let model = Model {
+            name: "events".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select_distinct_on(&model, &vec!["user_id".to_string()]);
+        compile_statement_with(&query, Dialect::MySQL);
+    }
+
+    #[test]
+    fn test_for_update() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model)
+            .for_update()
+            .where_clause(&"id = 1".to_string());
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE id = 1 FOR UPDATE"
+        );
+    }
+
+    #[test]
+    fn test_for_share_after_limit() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model)
+            .for_share()
+            .limit(10)
+            .order_by(&vec!["id".to_string()]);
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users ORDER BY id LIMIT 10 FOR SHARE"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicate FOR UPDATE/FOR SHARE clause")]
+    fn test_duplicate_lock() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        select(&model).for_update().for_share();
+    }
+
+    #[test]
+    fn test_serialize_query_block() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).where_clause(&"id = 1".to_string()).limit(5);
+        let json = serde_json::to_value(&query).unwrap();
+        assert_eq!(json["query_part"], "SELECT * FROM users");
+        assert_eq!(json["statement_type"], "Select");
+        assert_eq!(json["secondary_part"]["query_part"], "WHERE id = 1");
+        assert_eq!(json["secondary_part"]["clause_kind"], "Where");
+        assert_eq!(
+            json["secondary_part"]["secondary_part"]["query_part"],
+            "LIMIT 5"
+        );
+        assert!(json["secondary_part"]["secondary_part"]["secondary_part"].is_null());
+    }
+
+    #[test]
+    fn test_serialize_model() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["id".to_string()]),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&model).unwrap();
+        assert_eq!(json["name"], "users");
+        assert_eq!(json["fields"][0], "id");
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["id".to_string(), "name".to_string()]),
+            ..Default::default()
+        };
+        let query = select(&model)
+            .where_clause(&"name = 'bob'".to_string())
+            .order_by(&vec!["id".to_string()])
+            .limit(5);
+        let json = serde_json::to_string(&query).unwrap();
+        let reloaded: QueryBlock = serde_json::from_str(&json).unwrap();
+        assert!(matches!(reloaded.statement_type(), StatementType::Select));
+        assert_eq!(compile_statement(&reloaded), compile_statement(&query));
+        assert_eq!(
+            compile_parameterized(&reloaded),
+            compile_parameterized(&query)
+        );
+    }
+
+    #[test]
+    fn test_json_rejects_mismatched_query_part() {
+        let model = Model {
+            name: "a".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let mut json = serde_json::to_value(delete(&model)).unwrap();
+        json["query_part"] = "SELECT * FROM a".into();
+        let error = serde_json::from_value::<QueryBlock>(json).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "query part `SELECT * FROM a` does not match its parameterized form `DELETE FROM a`"
+        );
+    }
+
+    #[test]
+    fn test_json_round_trip_long_chain() {
+        let model = Model {
+            name: "t".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let mut query = select(&model).where_eq(&"id".to_string(), &Value::Int(1));
+        for index in 0..500 {
+            query = query.cross_join(&format!("t{}", index));
+        }
+        let json = serde_json::to_string(&query).unwrap();
+        let mut deserializer = serde_json::Deserializer::from_str(&json);
+        deserializer.disable_recursion_limit();
+        let reloaded = QueryBlock::deserialize(&mut deserializer).unwrap();
+        assert_eq!(reloaded, query);
+        assert_eq!(
+            compile_parameterized(&reloaded),
+            compile_parameterized(&query)
+        );
+    }
+
+    #[test]
+    fn test_compile_statement_pretty() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model)
+            .order_by(&vec!["id".to_string()])
+            .where_clause(&"id > 1".to_string());
+        assert_eq!(
+            compile_statement_pretty(&query),
+            "SELECT * FROM users\n  WHERE id > 1\n  ORDER BY id"
+        );
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE id > 1 ORDER BY id"
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_missing_where() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        assert_eq!(
+            validate(&delete(&model)),
+            Err(QueryError::MissingWhereClause {
+                statement: "DELETE FROM users".to_string()
+            })
+        );
+        assert_eq!(
+            validate(&delete(&model).where_clause(&"id = 1".to_string())),
+            Ok(())
+        );
+        assert_eq!(validate(&select(&model)), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_with_cte() {
+        let archived = Model {
+            name: "archived".to_string(),
+            fields: Some(vec!["id".to_string()]),
+            ..Default::default()
+        };
+        let users = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let guarded = with(
+            &"archived".to_string(),
+            select(&archived),
+            delete(&users).where_clause(&"id IN (SELECT id FROM archived)".to_string()),
+        );
+        assert_eq!(validate(&guarded), Ok(()));
+        let unguarded = with(&"archived".to_string(), select(&archived), delete(&users));
+        assert!(matches!(
+            validate(&unguarded),
+            Err(QueryError::MissingWhereClause { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_allow_full_scan() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["active".to_string()]),
+            ..Default::default()
+        };
+        let mut arguments = HashMap::new();
+        arguments.insert("active".to_string(), "false".to_string());
+        let query = update(&model).unwrap().set(&arguments).allow_full_scan();
+        assert_eq!(validate(&query), Ok(()));
+        assert_eq!(
+            compile_statement(&query),
+            "UPDATE users SET active = 'false'"
+        );
+    }
+
+    #[test]
+    fn test_query_builder_allow_full_scan() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = QueryBuilder::new(delete(&model)).allow_full_scan().build();
+        assert_eq!(validate(&query), Ok(()));
+        assert!(validate(&QueryBuilder::new(delete(&model)).build()).is_err());
+    }
+
+    #[test]
+    fn test_on_duplicate_key_update() {
+        let model = Model {
+            name: "counters".to_string(),
+            fields: Some(vec!["name".to_string(), "hits".to_string()]),
+            ..Default::default()
+        };
+        let mut updates = HashMap::new();
+        updates.insert("hits".to_string(), "hits + 1".to_string());
+        let query = insert(&model)
+            .on_duplicate_key_update(&updates)
+            .values(&vec!["home".to_string(), "1".to_string()]);
+        assert_eq!(
+            compile_statement_with(&query, Dialect::MySQL),
+            "INSERT INTO counters (name, hits) VALUES ('home', '1') ON DUPLICATE KEY UPDATE hits = hits + 1"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "is only supported by MySQL, not Postgres")]
+    fn test_on_duplicate_key_update_other_dialect() {
+        let model = Model {
+            name: "counters".to_string(),
+            fields: Some(vec!["hits".to_string()]),
+            ..Default::default()
+        };
+        let query = insert(&model).on_duplicate_key_update(&HashMap::new());
+        compile_statement_with(&query, Dialect::Postgres);
+    }
+
+    #[test]
+    fn test_insert_with_modes() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["id".to_string(), "name".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            compile_statement(&insert_with(&model, InsertMode::Default)),
+            compile_statement(&insert(&model))
+        );
+        assert_eq!(
+            compile_statement_with(&insert_with(&model, InsertMode::OrReplace), Dialect::Sqlite),
+            "INSERT OR REPLACE INTO users (id, name)"
+        );
+        assert_eq!(
+            compile_statement_with(&insert_with(&model, InsertMode::OrIgnore), Dialect::Sqlite),
+            "INSERT OR IGNORE INTO users (id, name)"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let by_name = |name: &str, age: &str| {
+            select(&model).where_cond(Condition::And(
+                Box::new(Condition::Eq("name".to_string(), name.to_string())),
+                Box::new(Condition::Gt("age".to_string(), age.to_string())),
+            ))
+        };
+        let by_bob = by_name("bob", "30");
+        let by_alice = by_name("alice", "41");
+        let by_email =
+            select(&model).where_eq(&"email".to_string(), &Value::Text("bob".to_string()));
+        assert_eq!(fingerprint(&by_bob), fingerprint(&by_alice));
+        assert_ne!(fingerprint(&by_bob), fingerprint(&by_email));
+        assert_ne!(
+            fingerprint(&by_bob),
+            fingerprint(&by_alice.order_by(&vec!["id".to_string()]))
+        );
+    }
 
-struct Model {
-    pub name: String,
-    pub fields: Option<Vec<String>>,
-}
+    #[test]
+    fn test_fingerprint_raw_where_clause() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let by_id = |id: &str| select(&model).where_clause(&format!("id = {}", id));
+        assert_eq!(fingerprint(&by_id("1")), fingerprint(&by_id("1")));
+        assert_ne!(fingerprint(&by_id("1")), fingerprint(&by_id("2")));
+    }
 
-trait SecondaryPart {
-    fn values(self, values: &Vec<String>) -> Self;
-    fn where_clause(self, where_clause: &String) -> Self;
-    fn set(self, arguments: &HashMap<String, String>) -> Self;
-}
+    #[test]
+    fn test_fingerprint_set_order() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["name".to_string()]),
+            ..Default::default()
+        };
+        let columns = ["name", "email", "age", "city", "zip"];
+        let forward: HashMap<String, String> = columns
+            .iter()
+            .map(|column| (column.to_string(), "x".to_string()))
+            .collect();
+        let backward: HashMap<String, String> = columns
+            .iter()
+            .rev()
+            .map(|column| (column.to_string(), "y".to_string()))
+            .collect();
+        let first = update(&model).unwrap().set(&forward);
+        let second = update(&model).unwrap().set(&backward);
+        assert_eq!(
+            compile_parameterized(&first).0,
+            "UPDATE users SET age = $1, city = $2, email = $3, name = $4, zip = $5"
+        );
+        assert_eq!(fingerprint(&first), fingerprint(&second));
+        assert_eq!(
+            compile_statement(&update(&model).unwrap().set_raw(&forward)),
+            "UPDATE users SET age = x, city = x, email = x, name = x, zip = x"
+        );
+    }
 
-impl SecondaryPart for QueryBlock {
-    fn values(mut self, values: &Vec<String>) -> Self {
-        let values_str = format!("VALUES ({})", values.join(", "));
-        let query_block = QueryBlock {
-            query_part: values_str,
-            secondary_part: None,
-            statement_type: self.statement_type.clone(),
+    #[test]
+    fn test_clone_query_block() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
         };
-        let latest_node = traverse_to_the_latest_node(&mut self);
-        latest_node.secondary_part = Some(Box::new(query_block));
-        self
+        let base = select(&model).where_eq(&"active".to_string(), &Value::Int(1));
+        let by_name = base.clone().order_by(&vec!["name".to_string()]);
+        let recent = base
+            .clone()
+            .where_cond(Condition::Lt("age".to_string(), "30".to_string()))
+            .limit(10);
+        assert_eq!(
+            compile_statement(&base),
+            "SELECT * FROM users WHERE active = 1"
+        );
+        assert_eq!(
+            compile_statement(&by_name),
+            "SELECT * FROM users WHERE active = 1 ORDER BY name"
+        );
+        assert_eq!(
+            compile_parameterized(&recent),
+            (
+                "SELECT * FROM users WHERE active = $1 AND age < $2 LIMIT 10".to_string(),
+                vec!["1".to_string(), "30".to_string()]
+            )
+        );
     }
 
-    fn set(mut self, arguments: &HashMap<String, String>) -> Self {
-        let set_clause = arguments
-            .iter()
-            .map(|(key, value)| format!("{} = {}", key, value))
-            .collect::<Vec<String>>()
-            .join(", ");
-        let set_clause_str = format!("SET {}", set_clause);
-        let query_block = QueryBlock {
-            query_part: set_clause_str,
-            secondary_part: None,
-            statement_type: self.statement_type.clone(),
+    #[test]
+    fn test_debug_query_block() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
         };
-        let latest_node = traverse_to_the_latest_node(&mut self);
-        latest_node.secondary_part = Some(Box::new(query_block));
-        self
+        let debug = format!("{:?}", select(&model).where_clause(&"id = 1".to_string()));
+        assert!(debug.starts_with("[QueryBlock { query_part: \"SELECT * FROM users\""));
+        assert!(debug.contains("statement_type: Select"));
+        assert!(debug.contains("clause_kind: Statement"));
+        assert!(debug.contains("}, QueryBlock { query_part: \"WHERE id = 1\""));
+        assert!(debug.contains("clause_kind: Where"));
+        assert!(format!("{:?}", model).starts_with("Model { name: \"users\", fields: None"));
     }
 
-    fn where_clause(mut self, where_clause: &String) -> Self {
-        let where_clause_str = format!("WHERE {}", where_clause);
+    #[test]
+    fn test_debug_long_chain() {
+        let model = Model {
+            name: "t".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let mut builder = QueryBuilder::new(select(&model));
+        for index in 0..20_000 {
+            builder = builder.cross_join(&format!("t{}", index));
+        }
+        let debug = format!("{:?}", builder.build());
+        assert_eq!(debug.matches("QueryBlock {").count(), 20_001);
+    }
 
-        let query_block = QueryBlock {
-            query_part: where_clause_str,
-            secondary_part: None,
-            statement_type: self.statement_type.clone(),
+    #[test]
+    fn test_query_block_eq() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
         };
-        let latest_node = traverse_to_the_latest_node(&mut self);
-        latest_node.secondary_part = Some(Box::new(query_block));
-        self
+        let build = || select(&model).where_clause(&"id = 1".to_string()).limit(1);
+        assert_eq!(build(), build());
+        assert_ne!(build(), select(&model).where_clause(&"id = 1".to_string()));
+        assert_ne!(
+            build(),
+            select(&model).where_clause(&"id = 2".to_string()).limit(1)
+        );
     }
-}
 
-fn select(model: &Model) -> QueryBlock {
-    match &model.fields {
-        Some(fields) => QueryBlock {
-            query_part: format!("SELECT {} FROM {}", fields.join(", "), model.name),
-            statement_type: StatementType::Select,
-            secondary_part: None,
-        },
-        None => QueryBlock {
-            query_part: format!("SELECT * FROM {}", model.name),
-            statement_type: StatementType::Select,
-            secondary_part: None,
-        },
+    #[test]
+    fn test_query_block_eq_compares_parameters_and_flags() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let raw = select(&model).where_clause(&"n = 'x'".to_string());
+        let bound = select(&model).where_eq(&"n".to_string(), &Value::Text("x".to_string()));
+        assert_eq!(compile_statement(&raw), compile_statement(&bound));
+        assert_ne!(raw, bound);
+        assert_ne!(delete(&model), delete(&model).allow_full_scan());
     }
-}
 
-fn update(model: &Model) -> QueryBlock {
-    match &model.fields {
-        Some(fields) => QueryBlock {
-            query_part: format!("UPDATE {}", model.name),
-            statement_type: StatementType::Update,
-            secondary_part: None,
-        },
-        None => panic!("Update query must have fields"),
+    #[test]
+    fn test_where_if() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).where_if(true, &"id = 1".to_string());
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE id = 1"
+        );
+        let query = select(&model).where_if(false, &"id = 1".to_string());
+        assert_eq!(compile_statement(&query), "SELECT * FROM users");
     }
-}
 
-fn delete(model: &Model) -> QueryBlock {
-    QueryBlock {
-        query_part: format!("DELETE FROM {}", model.name),
-        statement_type: StatementType::Delete,
-        secondary_part: None,
+    #[test]
+    fn test_where_if_combines_with_and() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model)
+            .where_if(true, &"age > 18".to_string())
+            .where_if(false, &"name = 'bob'".to_string())
+            .where_if(true, &"active = 1".to_string());
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE age > 18 AND active = 1"
+        );
     }
-}
 
-fn insert(model: &Model) -> QueryBlock {
-    let model = match &model.fields {
-        Some(fields) => QueryBlock {
-            query_part: format!("INSERT INTO {} ({})", model.name, fields.join(", ")),
-            statement_type: StatementType::Insert,
-            secondary_part: None,
-        },
-        None => QueryBlock {
-            query_part: format!("INSERT INTO {}", model.name),
-            statement_type: StatementType::Insert,
-            secondary_part: None,
-        },
-    };
-    model
-}
+    #[test]
+    fn test_where_eq_all() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let mut filters = HashMap::new();
+        filters.insert("status".to_string(), "active".to_string());
+        filters.insert("city".to_string(), "O'Fallon".to_string());
+        filters.insert("age".to_string(), "30".to_string());
+        let query = select(&model).where_eq_all(&filters);
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE age = '30' AND city = 'O''Fallon' AND status = 'active'"
+        );
+    }
 
-fn traverse_to_the_latest_node(statement: &mut QueryBlock) -> &mut QueryBlock {
-    let mut ret = statement;
-    while ret.secondary_part.is_some() {
-        ret = ret.secondary_part.as_mut().unwrap();
+    #[test]
+    fn test_order_by_checked() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model)
+            .order_by_checked("name", &["id", "name"])
+            .unwrap();
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users ORDER BY name"
+        );
     }
-    ret
-}
 
-fn compile_statement(statement: &QueryBlock) -> String {
-    fn helper(statement: &QueryBlock, acc: String) -> String {
-        match &statement.secondary_part {
-            Some(next_node) => helper(next_node, acc + " " + &statement.query_part),
-            None => acc + " " + &statement.query_part,
-        }
+    #[test]
+    fn test_order_by_checked_rejects_column() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let result = select(&model).order_by_checked("password; DROP TABLE users", &["id", "name"]);
+        assert_eq!(
+            result.unwrap_err(),
+            QueryError::DisallowedColumn {
+                column: "password; DROP TABLE users".to_string()
+            }
+        );
     }
-    helper(statement, "".to_string())[1..].to_string()
-}
 
-mod tests {
-    use super::*;
+    #[test]
+    fn test_select_checked() {
+        let allowed = ["id", "name", "email"];
+        let query = select_checked("users", &["name", "id"], &allowed).unwrap();
+        assert_eq!(compile_statement(&query), "SELECT name, id FROM users");
+        let query = select_checked("users", &[], &allowed).unwrap();
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT id, name, email FROM users"
+        );
+    }
 
     #[test]
-    fn test_select() {
+    fn test_select_checked_rejects_field() {
+        let result = select_checked("users", &["name", "password_hash"], &["id", "name"]);
+        assert_eq!(
+            result.unwrap_err(),
+            QueryError::DisallowedColumn {
+                column: "password_hash".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_count_query() {
         let model = Model {
             name: "users".to_string(),
+            fields: Some(vec!["id".to_string(), "name".to_string()]),
+            ..Default::default()
+        };
+        let page = select(&model)
+            .where_clause(&"active = 1".to_string())
+            .order_by(&vec!["name".to_string()])
+            .limit(20)
+            .offset(40);
+        let count = count_query(&page);
+        assert_eq!(
+            compile_statement(&count),
+            "SELECT COUNT(*) FROM (SELECT id, name FROM users WHERE active = 1) AS sub"
+        );
+        assert_eq!(
+            compile_statement(&page),
+            "SELECT id, name FROM users WHERE active = 1 ORDER BY name LIMIT 20 OFFSET 40"
+        );
+    }
+
+    #[test]
+    fn test_keyset_page() {
+        let model = Model {
+            name: "events".to_string(),
             fields: None,
+            ..Default::default()
         };
-        let query = select(&model);
-        assert_eq!(query.query_part, "SELECT * FROM users");
+        let query = select(&model)
+            .keyset_page(&"id".to_string(), &"120".to_string(), 50)
+            .where_clause(&"kind = 'click'".to_string());
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM events WHERE id > '120' AND kind = 'click' ORDER BY id LIMIT 50"
+        );
     }
 
     #[test]
-    fn test_select_with_fields() {
+    fn test_raw_fragment() {
         let model = Model {
             name: "users".to_string(),
-            fields: Some(vec!["id".to_string(), "name".to_string()]),
+            fields: None,
+            ..Default::default()
         };
-        let query = select(&model);
-        assert_eq!(query.query_part, "SELECT id, name FROM users");
+        let query = select(&model)
+            .raw(&"OPTION (RECOMPILE)".to_string())
+            .where_clause(&"id = 1".to_string())
+            .order_by(&vec!["id".to_string()]);
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE id = 1 ORDER BY id OPTION (RECOMPILE)"
+        );
     }
 
     #[test]
-    fn test_insert_values() {
+    fn test_string_agg() {
+        assert_eq!(string_agg("name", ", "), "STRING_AGG(name, ', ')");
+        assert_eq!(string_agg("name", "'"), "STRING_AGG(name, '''')");
+    }
+
+    #[test]
+    fn test_group_concat() {
+        assert_eq!(
+            group_concat("name", ", "),
+            "GROUP_CONCAT(name SEPARATOR ', ')"
+        );
+    }
+
+    #[test]
+    fn test_where_any() {
+        let users = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let admins = Model {
+            name: "admins".to_string(),
+            fields: Some(vec!["user_id".to_string()]),
+            ..Default::default()
+        };
+        let query = select(&users).where_any(&"id".to_string(), "=", select(&admins));
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE id = ANY (SELECT user_id FROM admins)"
+        );
+    }
+
+    #[test]
+    fn test_where_all() {
+        let products = Model {
+            name: "products".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let competitors = Model {
+            name: "competitors".to_string(),
+            fields: Some(vec!["price".to_string()]),
+            ..Default::default()
+        };
+        let subquery = select(&competitors).where_clause(&"region = 'eu'".to_string());
+        let query = select(&products).where_all(&"price".to_string(), ">", subquery);
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM products WHERE price > ALL (SELECT price FROM competitors WHERE region = 'eu')"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "unsupported comparison operator `; DROP TABLE users; --`")]
+    fn test_where_any_rejects_operator() {
         let model = Model {
             name: "users".to_string(),
             fields: None,
+            ..Default::default()
         };
-        let mut query = insert(&model).values(&vec!["1".to_string(), "John".to_string()]);
-        let compiled_query = compile_statement(&query);
-        assert_eq!(compiled_query, "INSERT INTO users VALUES (1, John)");
+        select(&model).where_any(&"id".to_string(), "; DROP TABLE users; --", select(&model));
     }
 
     #[test]
-    fn test_select_where_clause() {
+    fn test_select_from_subquery() {
+        let model = Model {
+            name: "orders".to_string(),
+            fields: Some(vec!["user_id".to_string(), sum("total")]),
+            ..Default::default()
+        };
+        let inner = select(&model)
+            .where_clause(&"status = 'paid'".to_string())
+            .group_by(&vec!["user_id".to_string()]);
+        let query = select_from_subquery(inner, "totals", Some(vec!["user_id".to_string()]))
+            .where_clause(&"total > 100".to_string());
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT user_id FROM (SELECT user_id, SUM(total) FROM orders WHERE status = 'paid' GROUP BY user_id) AS totals WHERE total > 100"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "derived table must have an alias")]
+    fn test_select_from_subquery_without_alias() {
+        let model = Model {
+            name: "orders".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        select_from_subquery(select(&model), "", None);
+    }
+
+    #[test]
+    fn test_left_join_lateral() {
+        let users = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["users.name".to_string(), "latest.total".to_string()]),
+            ..Default::default()
+        };
+        let orders = Model {
+            name: "orders".to_string(),
+            fields: Some(vec!["total".to_string()]),
+            ..Default::default()
+        };
+        let latest = select(&orders)
+            .where_clause(&"orders.user_id = users.id".to_string())
+            .order_by_with(&vec![("created_at".to_string(), OrderDirection::Desc)])
+            .limit(1);
+        let query = select(&users).join_lateral(latest, "latest", JoinKind::Left);
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT users.name, latest.total FROM users LEFT JOIN LATERAL (SELECT total FROM orders WHERE orders.user_id = users.id ORDER BY created_at DESC LIMIT 1) AS latest ON true"
+        );
+    }
+
+    #[test]
+    fn test_set_case_bulk_update() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["status".to_string()]),
+            ..Default::default()
+        };
+        let mapping = vec![
+            ("1".to_string(), "a".to_string()),
+            ("2".to_string(), "b".to_string()),
+        ];
+        let query =
+            update(&model)
+                .unwrap()
+                .set_case(&"status".to_string(), &"id".to_string(), &mapping);
+        assert_eq!(
+            compile_statement(&query),
+            "UPDATE users SET status = CASE id WHEN '1' THEN 'a' WHEN '2' THEN 'b' END WHERE id IN ('1', '2')"
+        );
+        assert_eq!(
+            compile_parameterized(&query),
+            (
+                "UPDATE users SET status = CASE id WHEN $1 THEN $2 WHEN $3 THEN $4 END WHERE id IN ($5, $6)"
+                    .to_string(),
+                vec!["1", "a", "2", "b", "1", "2"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            )
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "CASE mapping must have at least one entry")]
+    fn test_set_case_empty_mapping() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["status".to_string()]),
+            ..Default::default()
+        };
+        update(&model)
+            .unwrap()
+            .set_case(&"status".to_string(), &"id".to_string(), &vec![]);
+    }
+
+    #[test]
+    fn test_compile_statement_with_case() {
+        let model = Model {
+            name: "Users".to_string(),
+            fields: Some(vec!["id".to_string(), "\"Select\"".to_string()]),
+            ..Default::default()
+        };
+        let query = select(&model)
+            .where_clause(&"name = 'SELECT AND' and id in (1, 2)".to_string())
+            .where_null(&"deleted_at".to_string())
+            .order_by_with(&vec![("id".to_string(), OrderDirection::Desc)]);
+        assert_eq!(
+            compile_statement_with_case(&query, KeywordCase::Lower),
+            "select id, \"Select\" from Users where name = 'SELECT AND' and id in (1, 2) and deleted_at is null order by id desc"
+        );
+        assert_eq!(
+            compile_statement_with_case(&query, KeywordCase::Upper),
+            "SELECT id, \"Select\" FROM Users WHERE name = 'SELECT AND' and id in (1, 2) AND deleted_at IS NULL ORDER BY id DESC"
+        );
+    }
+
+    #[test]
+    fn test_compile_statement_with_case_keeps_identifiers() {
+        let model = Model {
+            name: "settings".to_string(),
+            fields: Some(vec![
+                "key".to_string(),
+                "first".to_string(),
+                "last".to_string(),
+                "end".to_string(),
+            ]),
+            ..Default::default()
+        };
+        let query = select(&model)
+            .where_in(&"key".to_string(), &vec!["a".to_string()])
+            .where_eq(&"end".to_string(), &Value::Bool(true))
+            .order_by_with_nulls(&vec![(
+                "last".to_string(),
+                OrderDirection::Asc,
+                Some(NullsPlacement::First),
+            )]);
+        assert_eq!(
+            compile_statement_with_case(&query, KeywordCase::Lower),
+            "select key, first, last, end from settings where key in ('a') and end = true order by last asc nulls first"
+        );
+    }
+
+    #[test]
+    fn test_compile_statement_terminated() {
         let model = Model {
             name: "users".to_string(),
             fields: None,
+            ..Default::default()
         };
-        let mut binding = select(&model);
         let query = select(&model).where_clause(&"id = 1".to_string());
-        let compiled_query = compile_statement(&query);
-        assert_eq!(compiled_query, "SELECT * FROM users WHERE id = 1");
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE id = 1"
+        );
+        assert_eq!(
+            compile_statement_terminated(&query),
+            "SELECT * FROM users WHERE id = 1;"
+        );
+        let query = select(&model).raw(&"LIMIT 1;".to_string());
+        assert_eq!(
+            compile_statement_terminated(&query),
+            "SELECT * FROM users LIMIT 1;"
+        );
     }
 
     #[test]
-    fn test_update_values() {
+    fn test_compile_batch() {
         let model = Model {
             name: "users".to_string(),
             fields: Some(vec!["name".to_string()]),
+            ..Default::default()
+        };
+        let create = raw_statement(
+            &crate::ddl::create_table(
+                &model,
+                &vec![crate::ddl::ColumnDef::new("name", "TEXT")],
+                &vec![],
+            )
+            .unwrap(),
+        );
+        let insert = insert(&model).values(&vec!["bob".to_string()]);
+        assert_eq!(
+            compile_batch(&[create, insert]),
+            "CREATE TABLE users (name TEXT);\nINSERT INTO users (name) VALUES ('bob')"
+        );
+        assert_eq!(compile_batch(&[select(&model)]), "SELECT name FROM users");
+        assert_eq!(compile_batch(&[]), "");
+    }
+
+    #[test]
+    fn test_compile_batch_trailing_whitespace() {
+        let batch = compile_batch(&[raw_statement("SELECT 1; "), raw_statement("SELECT 2")]);
+        assert_eq!(batch, "SELECT 1;\nSELECT 2");
+    }
+
+    #[test]
+    fn test_values_typed() {
+        let model = Model {
+            name: "items".to_string(),
+            fields: Some(vec![
+                "id".to_string(),
+                "price".to_string(),
+                "name".to_string(),
+                "active".to_string(),
+                "note".to_string(),
+            ]),
+            ..Default::default()
+        };
+        let query = insert(&model).values_typed(&vec![
+            Value::Int(7),
+            Value::Float(9.5),
+            Value::Text("it's".to_string()),
+            Value::Bool(true),
+            Value::Null,
+        ]);
+        assert_eq!(
+            compile_statement(&query),
+            "INSERT INTO items (id, price, name, active, note) VALUES (7, 9.5, 'it''s', TRUE, NULL)"
+        );
+        assert_eq!(
+            compile_parameterized(&query),
+            (
+                "INSERT INTO items (id, price, name, active, note) VALUES ($1, $2, $3, $4, NULL)"
+                    .to_string(),
+                vec![
+                    "7".to_string(),
+                    "9.5".to_string(),
+                    "it's".to_string(),
+                    "1".to_string()
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_insert_null() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["name".to_string(), "nickname".to_string()]),
+            ..Default::default()
+        };
+        let query = insert(&model).values_typed(&vec![Value::Text("bob".to_string()), Value::Null]);
+        assert_eq!(
+            compile_statement(&query),
+            "INSERT INTO users (name, nickname) VALUES ('bob', NULL)"
+        );
+    }
+
+    #[test]
+    fn test_update_null() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["nickname".to_string()]),
+            ..Default::default()
         };
         let mut arguments = HashMap::new();
-        arguments.insert("name".to_string(), "John".to_string());
-        let query = update(&model).set(&arguments);
-        let compiled_query = compile_statement(&query);
-        assert_eq!(compiled_query, "UPDATE users SET name = John");
+        arguments.insert("nickname".to_string(), Value::Null);
+        let query = update(&model)
+            .unwrap()
+            .set_typed(&arguments)
+            .where_eq(&"id".to_string(), &Value::Int(3));
+        assert_eq!(
+            compile_statement(&query),
+            "UPDATE users SET nickname = NULL WHERE id = 3"
+        );
     }
 
     #[test]
-    fn test_update_where_clause() {
+    fn test_where_eq_null() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).where_eq(&"nickname".to_string(), &Value::Null);
+        assert_eq!(
+            compile_statement(&query),
+            "SELECT * FROM users WHERE nickname IS NULL"
+        );
+    }
+
+    #[test]
+    fn test_boolean_per_dialect() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["name".to_string(), "active".to_string()]),
+            ..Default::default()
+        };
+        let query =
+            insert(&model).values_typed(&vec![Value::Text("true".to_string()), Value::Bool(true)]);
+        assert_eq!(
+            compile_statement_with(&query, Dialect::Postgres),
+            "INSERT INTO users (name, active) VALUES ('true', TRUE)"
+        );
+        assert_eq!(
+            compile_statement_with(&query, Dialect::Sqlite),
+            "INSERT INTO users (name, active) VALUES ('true', 1)"
+        );
         let mut arguments = HashMap::new();
-        arguments.insert("id".to_string(), "2".to_string());
-        let mut where_string = "id = 1".to_string();
-        let fields = Some(vec!["id".to_string()]);
+        arguments.insert("active".to_string(), Value::Bool(false));
+        let query = update(&model)
+            .unwrap()
+            .set_typed(&arguments)
+            .where_clause(&"id = 1".to_string());
+        assert_eq!(
+            compile_statement_with(&query, Dialect::Sqlite),
+            "UPDATE users SET active = 0 WHERE id = 1"
+        );
+        assert_eq!(
+            compile_statement_with(&query, Dialect::MySQL),
+            "UPDATE users SET active = FALSE WHERE id = 1"
+        );
+        assert_eq!(
+            compile_parameterized(&query),
+            (
+                "UPDATE users SET active = $1 WHERE id = 1".to_string(),
+                vec!["0".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn test_boolean_keywords_in_raw_sql_are_kept() {
         let model = Model {
             name: "users".to_string(),
-            fields: fields,
+            fields: None,
+            ..Default::default()
         };
-        let mut query = update(&model).set(&arguments).where_clause(&where_string);
-        let compiled_query = compile_statement(&query);
-        assert_eq!(compiled_query, "UPDATE users SET id = 2 WHERE id = 1");
+        let query = select(&model)
+            .join_lateral(select(&model).limit(1), "latest", JoinKind::Left)
+            .where_clause(&"verified = true OR legacy IS FALSE".to_string());
+        assert_eq!(
+            compile_statement_with(&query, Dialect::Sqlite),
+            "SELECT * FROM users LEFT JOIN LATERAL (SELECT * FROM users LIMIT 1) AS latest ON true WHERE verified = true OR legacy IS FALSE"
+        );
+    }
+
+    #[test]
+    fn test_model_builder() {
+        let model = Model::builder()
+            .name("users")
+            .fields(&["u.id", "u.name"])
+            .alias("u")
+            .schema("public")
+            .quote_identifiers(false)
+            .build();
+        assert_eq!(model.name, "users");
+        assert_eq!(model.alias.as_deref(), Some("u"));
+        assert_eq!(model.schema.as_deref(), Some("public"));
+        assert_eq!(
+            compile_statement(&select(&model)),
+            "SELECT u.id, u.name FROM public.users AS u"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Model must have a name")]
+    fn test_model_builder_without_name() {
+        Model::builder().fields(&["id"]).build();
+    }
+
+    #[test]
+    fn test_merge() {
+        let target = Model {
+            name: "accounts".to_string(),
+            fields: None,
+            alias: Some("t".to_string()),
+            ..Default::default()
+        };
+        let mut updates = HashMap::new();
+        updates.insert("balance".to_string(), "s.balance".to_string());
+        let query = merge(&target, "staged_accounts AS s", &"t.id = s.id".to_string())
+            .when_not_matched_insert(
+                &vec!["id".to_string(), "balance".to_string()],
+                &vec!["s.id".to_string(), "s.balance".to_string()],
+            )
+            .when_matched_update(&updates);
+        assert_eq!(
+            compile_statement(&query),
+            "MERGE INTO accounts AS t USING staged_accounts AS s ON t.id = s.id WHEN MATCHED THEN UPDATE SET balance = s.balance WHEN NOT MATCHED THEN INSERT (id, balance) VALUES (s.id, s.balance)"
+        );
+        assert_eq!(
+            compile_statement_with_case(&query, KeywordCase::Lower),
+            "merge into accounts as t using staged_accounts AS s on t.id = s.id when matched then update set balance = s.balance when not matched then insert (id, balance) values (s.id, s.balance)"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "WHEN MATCHED clause can only be added to MERGE statements")]
+    fn test_when_matched_on_update() {
+        let model = Model {
+            name: "accounts".to_string(),
+            fields: Some(vec!["balance".to_string()]),
+            ..Default::default()
+        };
+        update(&model).unwrap().when_matched_update(&HashMap::new());
+    }
+
+    #[test]
+    fn test_explain() {
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let query = select(&model).where_clause(&"id = 1".to_string());
+        assert_eq!(
+            explain(&query, false),
+            "EXPLAIN SELECT * FROM users WHERE id = 1"
+        );
+        assert_eq!(
+            explain(&query, true),
+            "EXPLAIN ANALYZE SELECT * FROM users WHERE id = 1"
+        );
     }
 }
@@ -0,0 +1,628 @@
+#![allow(clippy::ptr_arg)]
+
+use crate::ast_builder::{compile_statement, Model, QueryBlock, QueryError};
+
+// Schema statements are plain strings rather than `QueryBlock` chains:
+// they have no clauses to reorder and nothing to parameterize.
+
+#[derive(Clone, Debug)]
+pub struct ColumnDef {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+    pub primary_key: bool,
+    // Rendered verbatim, so function defaults such as `now()` work and
+    // string defaults must carry their own quotes.
+    pub default: Option<String>,
+}
+
+impl ColumnDef {
+    pub fn new(name: &str, data_type: &str) -> Self {
+        ColumnDef {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            nullable: true,
+            primary_key: false,
+            default: None,
+        }
+    }
+
+    // The DEFAULT clause precedes the column constraints, as in standard
+    // SQL. A primary key is implicitly NOT NULL, so the constraint is only
+    // spelled out for ordinary columns.
+    fn to_sql(&self) -> String {
+        self.definition(&self.name)
+    }
+
+    // `name` is the column name as it should appear in the statement, so a
+    // caller can quote it.
+    fn definition(&self, name: &str) -> String {
+        let mut sql = format!("{} {}", name, self.data_type);
+        if let Some(default) = &self.default {
+            sql.push_str(&format!(" DEFAULT {}", default));
+        }
+        if self.primary_key {
+            sql.push_str(" PRIMARY KEY");
+        } else if !self.nullable {
+            sql.push_str(" NOT NULL");
+        }
+        sql
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ReferentialAction {
+    Cascade,
+    SetNull,
+    Restrict,
+    NoAction,
+}
+
+impl ReferentialAction {
+    fn keyword(&self) -> &'static str {
+        match self {
+            ReferentialAction::Cascade => "CASCADE",
+            ReferentialAction::SetNull => "SET NULL",
+            ReferentialAction::Restrict => "RESTRICT",
+            ReferentialAction::NoAction => "NO ACTION",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ForeignKey {
+    pub columns: Vec<String>,
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+    pub on_delete: Option<ReferentialAction>,
+}
+
+impl ForeignKey {
+    pub fn new(columns: &[&str], referenced_table: &str, referenced_columns: &[&str]) -> Self {
+        ForeignKey {
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            referenced_table: referenced_table.to_string(),
+            referenced_columns: referenced_columns
+                .iter()
+                .map(|column| column.to_string())
+                .collect(),
+            on_delete: None,
+        }
+    }
+
+    pub fn on_delete(mut self, action: ReferentialAction) -> Self {
+        self.on_delete = Some(action);
+        self
+    }
+
+    fn to_sql(&self, model: &Model) -> String {
+        let mut sql = format!(
+            "FOREIGN KEY ({}) REFERENCES {} ({})",
+            identifier_list(model, &self.columns),
+            model.identifier(&self.referenced_table),
+            identifier_list(model, &self.referenced_columns)
+        );
+        if let Some(action) = &self.on_delete {
+            sql.push_str(&format!(" ON DELETE {}", action.keyword()));
+        }
+        sql
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Constraint {
+    Unique(Vec<String>),
+    Check(String),
+    ForeignKey(ForeignKey),
+}
+
+impl Constraint {
+    // The table's own columns this constraint names; a CHECK condition is
+    // raw SQL and is not inspected.
+    fn columns(&self) -> &[String] {
+        match self {
+            Constraint::Unique(columns) => columns,
+            Constraint::Check(_) => &[],
+            Constraint::ForeignKey(foreign_key) => &foreign_key.columns,
+        }
+    }
+
+    fn to_sql(&self, model: &Model) -> String {
+        match self {
+            Constraint::Unique(columns) => format!("UNIQUE ({})", identifier_list(model, columns)),
+            Constraint::Check(condition) => format!("CHECK ({})", condition),
+            Constraint::ForeignKey(foreign_key) => foreign_key.to_sql(model),
+        }
+    }
+}
+
+// Creates the model's table, with the name and identifiers rendered as the
+// model's queries render them. The primary key comes from the model when it
+// declares one: a single-column key is declared on the column itself, a
+// composite one as a table-level constraint ahead of `constraints`. Every
+// column named by the key or a constraint must be among `columns`. With
+// table-level constraints the statement is laid out one definition per
+// line, columns first.
+pub fn create_table(
+    model: &Model,
+    columns: &Vec<ColumnDef>,
+    constraints: &Vec<Constraint>,
+) -> Result<String, QueryError> {
+    let mut columns = columns.clone();
+    let keys = model.primary_key.clone().unwrap_or_default();
+    let referenced = keys
+        .iter()
+        .chain(constraints.iter().flat_map(Constraint::columns));
+    for key in referenced {
+        if !columns.iter().any(|column| &column.name == key) {
+            return Err(QueryError::UndefinedColumn {
+                column: key.clone(),
+            });
+        }
+    }
+    if let [key] = keys.as_slice() {
+        for column in columns.iter_mut() {
+            column.primary_key = &column.name == key;
+        }
+    }
+    let mut definitions = columns
+        .iter()
+        .map(|column| column.definition(&model.identifier(&column.name)))
+        .collect::<Vec<String>>();
+    if keys.len() > 1 {
+        definitions.push(format!("PRIMARY KEY ({})", identifier_list(model, &keys)));
+    }
+    if constraints.is_empty() {
+        return Ok(format!(
+            "CREATE TABLE {} ({})",
+            model.table_name(),
+            definitions.join(", ")
+        ));
+    }
+    definitions.extend(
+        constraints
+            .iter()
+            .map(|constraint| constraint.to_sql(model)),
+    );
+    Ok(format!(
+        "CREATE TABLE {} (\n  {}\n)",
+        model.table_name(),
+        definitions.join(",\n  ")
+    ))
+}
+
+fn identifier_list(model: &Model, names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| model.identifier(name))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+pub fn drop_table(name: &str, if_exists: bool) -> String {
+    if if_exists {
+        format!("DROP TABLE IF EXISTS {}", name)
+    } else {
+        format!("DROP TABLE {}", name)
+    }
+}
+
+pub fn add_column(table: &str, column: &ColumnDef) -> String {
+    format!("ALTER TABLE {} ADD COLUMN {}", table, column.to_sql())
+}
+
+pub fn create_index(name: &str, table: &str, columns: &Vec<String>, unique: bool) -> String {
+    let keyword = if unique {
+        "CREATE UNIQUE INDEX"
+    } else {
+        "CREATE INDEX"
+    };
+    format!("{} {} ON {} ({})", keyword, name, table, columns.join(", "))
+}
+
+pub fn create_view(name: &str, query: QueryBlock, or_replace: bool) -> String {
+    let keyword = if or_replace {
+        "CREATE OR REPLACE VIEW"
+    } else {
+        "CREATE VIEW"
+    };
+    format!("{} {} AS {}", keyword, name, compile_statement(&query))
+}
+
+pub fn drop_column(table: &str, column: &str) -> String {
+    format!("ALTER TABLE {} DROP COLUMN {}", table, column)
+}
+
+// Postgres syntax; MySQL spells this `MODIFY COLUMN`.
+pub fn alter_column_type(table: &str, column: &str, new_type: &str) -> String {
+    format!(
+        "ALTER TABLE {} ALTER COLUMN {} TYPE {}",
+        table, column, new_type
+    )
+}
+
+pub fn rename_table(old: &str, new: &str) -> String {
+    format!("ALTER TABLE {} RENAME TO {}", old, new)
+}
+
+pub fn rename_column(table: &str, old: &str, new: &str) -> String {
+    format!("ALTER TABLE {} RENAME COLUMN {} TO {}", table, old, new)
+}
+
+// MySQL and SQL Server name the table the index belongs to.
+pub fn drop_index(name: &str, if_exists: bool, table: Option<&str>) -> String {
+    let mut sql = if if_exists {
+        format!("DROP INDEX IF EXISTS {}", name)
+    } else {
+        format!("DROP INDEX {}", name)
+    };
+    if let Some(table) = table {
+        sql.push_str(&format!(" ON {}", table));
+    }
+    sql
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_table() {
+        let id = ColumnDef {
+            primary_key: true,
+            ..ColumnDef::new("id", "INTEGER")
+        };
+        let name = ColumnDef {
+            nullable: false,
+            ..ColumnDef::new("name", "TEXT")
+        };
+        let model = Model::builder().name("users").build();
+        assert_eq!(
+            create_table(&model, &vec![id, name], &vec![]),
+            Ok("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_create_table_single_primary_key() {
+        let model = Model::builder().name("users").primary_key(&["id"]).build();
+        let columns = vec![
+            ColumnDef::new("id", "INTEGER"),
+            ColumnDef::new("name", "TEXT"),
+        ];
+        assert_eq!(
+            create_table(&model, &columns, &vec![]),
+            Ok("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_create_table_missing_primary_key_column() {
+        let columns = vec![ColumnDef::new("name", "TEXT")];
+        let single = Model::builder().name("users").primary_key(&["id"]).build();
+        let composite = Model::builder()
+            .name("users")
+            .primary_key(&["name", "tenant_id"])
+            .build();
+        assert_eq!(
+            create_table(&single, &columns, &vec![]),
+            Err(QueryError::UndefinedColumn {
+                column: "id".to_string()
+            })
+        );
+        assert_eq!(
+            create_table(&composite, &columns, &vec![]),
+            Err(QueryError::UndefinedColumn {
+                column: "tenant_id".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_create_table_quoted_schema() {
+        let model = Model::builder()
+            .name("users")
+            .schema("app")
+            .primary_key(&["id", "tenant id"])
+            .quote_identifiers(true)
+            .build();
+        let columns = vec![
+            ColumnDef::new("id", "INTEGER"),
+            ColumnDef::new("tenant id", "INTEGER"),
+        ];
+        assert_eq!(
+            create_table(&model, &columns, &vec![]),
+            Ok("CREATE TABLE \"app\".\"users\" (\"id\" INTEGER, \"tenant id\" INTEGER, PRIMARY KEY (\"id\", \"tenant id\"))".to_string())
+        );
+    }
+
+    #[test]
+    fn test_create_table_composite_primary_key() {
+        let model = Model::builder()
+            .name("memberships")
+            .primary_key(&["user_id", "group_id"])
+            .build();
+        let columns = vec![
+            ColumnDef {
+                nullable: false,
+                ..ColumnDef::new("user_id", "INTEGER")
+            },
+            ColumnDef {
+                nullable: false,
+                ..ColumnDef::new("group_id", "INTEGER")
+            },
+        ];
+        assert_eq!(
+            create_table(&model, &columns, &vec![]),
+            Ok("CREATE TABLE memberships (user_id INTEGER NOT NULL, group_id INTEGER NOT NULL, PRIMARY KEY (user_id, group_id))".to_string())
+        );
+    }
+
+    #[test]
+    fn test_create_table_with_foreign_key() {
+        let columns = vec![
+            ColumnDef {
+                primary_key: true,
+                ..ColumnDef::new("id", "INTEGER")
+            },
+            ColumnDef {
+                nullable: false,
+                ..ColumnDef::new("user_id", "INTEGER")
+            },
+        ];
+        let constraints = vec![Constraint::ForeignKey(
+            ForeignKey::new(&["user_id"], "users", &["id"]).on_delete(ReferentialAction::Cascade),
+        )];
+        let model = Model::builder().name("posts").build();
+        assert_eq!(
+            create_table(&model, &columns, &constraints).unwrap(),
+            "CREATE TABLE posts (\n  id INTEGER PRIMARY KEY,\n  user_id INTEGER NOT NULL,\n  FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE\n)"
+        );
+    }
+
+    #[test]
+    fn test_create_table_composite_primary_key_with_foreign_keys() {
+        let model = Model::builder()
+            .name("memberships")
+            .primary_key(&["user_id", "group_id"])
+            .build();
+        let columns = vec![
+            ColumnDef::new("user_id", "INTEGER"),
+            ColumnDef::new("group_id", "INTEGER"),
+        ];
+        let constraints = vec![
+            Constraint::ForeignKey(ForeignKey::new(&["user_id"], "users", &["id"])),
+            Constraint::ForeignKey(
+                ForeignKey::new(&["group_id"], "groups", &["id"])
+                    .on_delete(ReferentialAction::Cascade),
+            ),
+        ];
+        assert_eq!(
+            create_table(&model, &columns, &constraints).unwrap(),
+            concat!(
+                "CREATE TABLE memberships (\n",
+                "  user_id INTEGER,\n",
+                "  group_id INTEGER,\n",
+                "  PRIMARY KEY (user_id, group_id),\n",
+                "  FOREIGN KEY (user_id) REFERENCES users (id),\n",
+                "  FOREIGN KEY (group_id) REFERENCES groups (id) ON DELETE CASCADE\n",
+                ")"
+            )
+        );
+    }
+
+    #[test]
+    fn test_create_table_undefined_constraint_column() {
+        let model = Model::builder().name("posts").build();
+        let constraints = vec![Constraint::ForeignKey(ForeignKey::new(
+            &["author_id"],
+            "users",
+            &["id"],
+        ))];
+        assert_eq!(
+            create_table(&model, &vec![ColumnDef::new("id", "INTEGER")], &constraints),
+            Err(QueryError::UndefinedColumn {
+                column: "author_id".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_create_table_literal_default() {
+        let status = ColumnDef {
+            nullable: false,
+            default: Some("'active'".to_string()),
+            ..ColumnDef::new("status", "TEXT")
+        };
+        let model = Model::builder().name("users").build();
+        assert_eq!(
+            create_table(&model, &vec![status], &vec![]).unwrap(),
+            "CREATE TABLE users (status TEXT DEFAULT 'active' NOT NULL)"
+        );
+    }
+
+    #[test]
+    fn test_create_table_function_default() {
+        let created_at = ColumnDef {
+            default: Some("now()".to_string()),
+            ..ColumnDef::new("created_at", "TIMESTAMP")
+        };
+        let model = Model::builder().name("events").build();
+        assert_eq!(
+            create_table(&model, &vec![created_at], &vec![]).unwrap(),
+            "CREATE TABLE events (created_at TIMESTAMP DEFAULT now())"
+        );
+    }
+
+    #[test]
+    fn test_create_table_unique_constraint() {
+        let columns = vec![
+            ColumnDef::new("email", "TEXT"),
+            ColumnDef::new("tenant_id", "INTEGER"),
+        ];
+        let constraints = vec![Constraint::Unique(vec![
+            "tenant_id".to_string(),
+            "email".to_string(),
+        ])];
+        let model = Model::builder().name("users").build();
+        assert_eq!(
+            create_table(&model, &columns, &constraints).unwrap(),
+            "CREATE TABLE users (\n  email TEXT,\n  tenant_id INTEGER,\n  UNIQUE (tenant_id, email)\n)"
+        );
+    }
+
+    #[test]
+    fn test_create_table_check_constraint() {
+        let columns = vec![ColumnDef::new("age", "INTEGER")];
+        let constraints = vec![
+            Constraint::Check("age >= 0".to_string()),
+            Constraint::Unique(vec!["age".to_string()]),
+        ];
+        let model = Model::builder().name("people").build();
+        assert_eq!(
+            create_table(&model, &columns, &constraints).unwrap(),
+            "CREATE TABLE people (\n  age INTEGER,\n  CHECK (age >= 0),\n  UNIQUE (age)\n)"
+        );
+    }
+
+    #[test]
+    fn test_create_table_mixed_constraints() {
+        let model = Model::builder()
+            .name("accounts")
+            .primary_key(&["id"])
+            .quote_identifiers(true)
+            .build();
+        let columns = vec![
+            ColumnDef::new("id", "INTEGER"),
+            ColumnDef::new("email", "TEXT"),
+            ColumnDef::new("owner_id", "INTEGER"),
+        ];
+        let constraints = vec![
+            Constraint::Unique(vec!["email".to_string()]),
+            Constraint::Check("length(email) > 3".to_string()),
+            Constraint::ForeignKey(ForeignKey::new(&["owner_id"], "users", &["id"])),
+        ];
+        assert_eq!(
+            create_table(&model, &columns, &constraints).unwrap(),
+            concat!(
+                "CREATE TABLE \"accounts\" (\n",
+                "  \"id\" INTEGER PRIMARY KEY,\n",
+                "  \"email\" TEXT,\n",
+                "  \"owner_id\" INTEGER,\n",
+                "  UNIQUE (\"email\"),\n",
+                "  CHECK (length(email) > 3),\n",
+                "  FOREIGN KEY (\"owner_id\") REFERENCES \"users\" (\"id\")\n",
+                ")"
+            )
+        );
+    }
+
+    #[test]
+    fn test_drop_table() {
+        assert_eq!(drop_table("users", false), "DROP TABLE users");
+        assert_eq!(drop_table("users", true), "DROP TABLE IF EXISTS users");
+    }
+
+    #[test]
+    fn test_add_column() {
+        let age = ColumnDef {
+            nullable: false,
+            default: Some("0".to_string()),
+            ..ColumnDef::new("age", "INTEGER")
+        };
+        assert_eq!(
+            add_column("users", &age),
+            "ALTER TABLE users ADD COLUMN age INTEGER DEFAULT 0 NOT NULL"
+        );
+    }
+
+    #[test]
+    fn test_create_index() {
+        assert_eq!(
+            create_index("idx", "users", &vec!["name".to_string()], false),
+            "CREATE INDEX idx ON users (name)"
+        );
+    }
+
+    #[test]
+    fn test_create_multi_column_index() {
+        let columns = vec!["last_name".to_string(), "first_name".to_string()];
+        assert_eq!(
+            create_index("idx_name", "users", &columns, false),
+            "CREATE INDEX idx_name ON users (last_name, first_name)"
+        );
+    }
+
+    #[test]
+    fn test_create_unique_index() {
+        assert_eq!(
+            create_index("idx_email", "users", &vec!["email".to_string()], true),
+            "CREATE UNIQUE INDEX idx_email ON users (email)"
+        );
+    }
+
+    #[test]
+    fn test_drop_index() {
+        assert_eq!(drop_index("idx", false, None), "DROP INDEX idx");
+        assert_eq!(drop_index("idx", true, None), "DROP INDEX IF EXISTS idx");
+    }
+
+    #[test]
+    fn test_drop_index_on_table() {
+        assert_eq!(
+            drop_index("idx_email", false, Some("users")),
+            "DROP INDEX idx_email ON users"
+        );
+    }
+
+    #[test]
+    fn test_rename_table() {
+        assert_eq!(
+            rename_table("users", "accounts"),
+            "ALTER TABLE users RENAME TO accounts"
+        );
+    }
+
+    #[test]
+    fn test_rename_column() {
+        assert_eq!(
+            rename_column("users", "mail", "email"),
+            "ALTER TABLE users RENAME COLUMN mail TO email"
+        );
+    }
+
+    #[test]
+    fn test_drop_column() {
+        assert_eq!(
+            drop_column("users", "age"),
+            "ALTER TABLE users DROP COLUMN age"
+        );
+    }
+
+    #[test]
+    fn test_alter_column_type() {
+        assert_eq!(
+            alter_column_type("users", "age", "BIGINT"),
+            "ALTER TABLE users ALTER COLUMN age TYPE BIGINT"
+        );
+    }
+
+    #[test]
+    fn test_create_view() {
+        use crate::ast_builder::{select, SecondaryPart};
+
+        let model = Model::builder()
+            .name("users")
+            .fields(&["id", "name"])
+            .build();
+        let active = || select(&model).where_clause(&"active = 1".to_string());
+        assert_eq!(
+            create_view("active_users", active(), false),
+            "CREATE VIEW active_users AS SELECT id, name FROM users WHERE active = 1"
+        );
+        assert_eq!(
+            create_view("active_users", active(), true),
+            "CREATE OR REPLACE VIEW active_users AS SELECT id, name FROM users WHERE active = 1"
+        );
+    }
+}
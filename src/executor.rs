@@ -0,0 +1,145 @@
+use crate::ast_builder::{compile_parameterized, QueryBlock};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+pub type Row = HashMap<String, String>;
+
+#[derive(Debug)]
+pub enum ExecError {
+    Backend(Box<dyn Error + Send + Sync>),
+    InvalidSavepoint { name: String },
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecError::Backend(error) => write!(f, "database error: {}", error),
+            ExecError::InvalidSavepoint { name } => {
+                write!(f, "`{}` is not a valid savepoint name", name)
+            }
+        }
+    }
+}
+
+impl Error for ExecError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExecError::Backend(error) => Some(error.as_ref()),
+            ExecError::InvalidSavepoint { .. } => None,
+        }
+    }
+}
+
+// `execute` is for statements that modify rows and returns how many were
+// affected; `query` is for statements that return rows. `execute_batch`
+// runs a script of several `;`-separated statements without parameters.
+pub trait Executor {
+    fn execute(&self, sql: &str, params: &[String]) -> Result<u64, ExecError>;
+    fn query(&self, sql: &str, params: &[String]) -> Result<Vec<Row>, ExecError>;
+    fn execute_batch(&self, sql: &str) -> Result<(), ExecError>;
+
+    fn execute_statement(&self, statement: &QueryBlock) -> Result<u64, ExecError> {
+        let (sql, params) = compile_parameterized(statement);
+        self.execute(&sql, &params)
+    }
+
+    fn query_statement(&self, statement: &QueryBlock) -> Result<Vec<Row>, ExecError> {
+        let (sql, params) = compile_parameterized(statement);
+        self.query(&sql, &params)
+    }
+}
+
+// Records every statement it is given and answers with canned results, so
+// code built on `Executor` can be tested without a database.
+#[derive(Default)]
+pub struct MockExecutor {
+    log: RefCell<Vec<(String, Vec<String>)>>,
+    rows: Vec<Row>,
+    affected: u64,
+}
+
+impl MockExecutor {
+    pub fn new() -> Self {
+        MockExecutor::default()
+    }
+
+    pub fn with_rows(mut self, rows: Vec<Row>) -> Self {
+        self.rows = rows;
+        self
+    }
+
+    pub fn with_affected(mut self, affected: u64) -> Self {
+        self.affected = affected;
+        self
+    }
+
+    pub fn log(&self) -> Vec<(String, Vec<String>)> {
+        self.log.borrow().clone()
+    }
+
+    fn record(&self, sql: &str, params: &[String]) {
+        self.log
+            .borrow_mut()
+            .push((sql.to_string(), params.to_vec()));
+    }
+}
+
+impl Executor for MockExecutor {
+    fn execute(&self, sql: &str, params: &[String]) -> Result<u64, ExecError> {
+        self.record(sql, params);
+        Ok(self.affected)
+    }
+
+    fn query(&self, sql: &str, params: &[String]) -> Result<Vec<Row>, ExecError> {
+        self.record(sql, params);
+        Ok(self.rows.clone())
+    }
+
+    fn execute_batch(&self, sql: &str) -> Result<(), ExecError> {
+        self.record(sql, &[]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_builder::*;
+
+    #[test]
+    fn test_mock_executor() {
+        let mut row = Row::new();
+        row.insert("name".to_string(), "bob".to_string());
+        let executor = MockExecutor::new().with_rows(vec![row]).with_affected(2);
+
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["name".to_string()]),
+            ..Default::default()
+        };
+        let (sql, params) =
+            compile_parameterized(&select(&model).where_eq(&"id".to_string(), &Value::Int(1)));
+        let rows = executor.query(&sql, &params).unwrap();
+        assert_eq!(rows[0]["name"], "bob");
+
+        let (sql, params) =
+            compile_parameterized(&delete(&model).where_eq(&"id".to_string(), &Value::Int(1)));
+        assert_eq!(executor.execute(&sql, &params).unwrap(), 2);
+
+        assert_eq!(
+            executor.log(),
+            vec![
+                (
+                    "SELECT name FROM users WHERE id = $1".to_string(),
+                    vec!["1".to_string()]
+                ),
+                (
+                    "DELETE FROM users WHERE id = $1".to_string(),
+                    vec!["1".to_string()]
+                ),
+            ]
+        );
+    }
+}
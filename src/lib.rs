@@ -0,0 +1,21 @@
+pub mod ast_builder;
+pub mod ddl;
+pub mod executor;
+mod macros;
+pub mod migration;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod transaction;
+
+pub use ast_builder::*;
+pub use ddl::*;
+pub use executor::*;
+pub use migration::*;
+pub use my_orm_derive::Model;
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresExecutor;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteExecutor;
+pub use transaction::*;
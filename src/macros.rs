@@ -0,0 +1,74 @@
+// `sql!(select users ["id", "name"] where "id = 1" order_by ["name"])`
+// expands to the equivalent chain of constructor and `SecondaryPart` calls.
+// The head is one of `select`, `insert`, `update` or `delete` followed by
+// the table name and, for `select` and `insert`, an optional field list.
+// For `update` the fields come from the `set` clause.
+#[macro_export]
+macro_rules! sql {
+    (select $table:ident [$($field:expr),* $(,)?] $($rest:tt)*) => {
+        $crate::sql!(@clauses $crate::select(&$crate::sql!(@model $table [$($field),*])); $($rest)*)
+    };
+    (select $table:ident $($rest:tt)*) => {
+        $crate::sql!(@clauses $crate::select(&$crate::sql!(@model $table)); $($rest)*)
+    };
+    (insert $table:ident [$($field:expr),* $(,)?] $($rest:tt)*) => {
+        $crate::sql!(@clauses $crate::insert(&$crate::sql!(@model $table [$($field),*])); $($rest)*)
+    };
+    (insert $table:ident $($rest:tt)*) => {
+        $crate::sql!(@clauses $crate::insert(&$crate::sql!(@model $table)); $($rest)*)
+    };
+    (update $table:ident $($rest:tt)*) => {
+        $crate::sql!(
+            @clauses $crate::update(&$crate::sql!(@model $table [])).unwrap(); $($rest)*
+        )
+    };
+    (delete $table:ident $($rest:tt)*) => {
+        $crate::sql!(@clauses $crate::delete(&$crate::sql!(@model $table)); $($rest)*)
+    };
+
+    (@model $table:ident) => {
+        $crate::Model {
+            name: stringify!($table).to_string(),
+            ..Default::default()
+        }
+    };
+    (@model $table:ident [$($field:expr),*]) => {
+        $crate::Model {
+            name: stringify!($table).to_string(),
+            fields: Some(vec![$($field.to_string()),*]),
+            ..Default::default()
+        }
+    };
+
+    (@clauses $query:expr;) => {
+        $query
+    };
+    (@clauses $query:expr; where $cond:literal $($rest:tt)*) => {
+        $crate::sql!(@clauses $crate::SecondaryPart::where_clause($query, &$cond.to_string()); $($rest)*)
+    };
+    (@clauses $query:expr; set {$($key:literal => $value:expr),* $(,)?} $($rest:tt)*) => {
+        $crate::sql!(@clauses $crate::SecondaryPart::set($query, &{
+            let mut arguments = ::std::collections::HashMap::new();
+            $(arguments.insert($key.to_string(), $value.to_string());)*
+            arguments
+        }); $($rest)*)
+    };
+    (@clauses $query:expr; values [$($value:expr),* $(,)?] $($rest:tt)*) => {
+        $crate::sql!(@clauses $crate::SecondaryPart::values($query, &vec![$($value.to_string()),*]); $($rest)*)
+    };
+    (@clauses $query:expr; order_by [$($column:expr),* $(,)?] $($rest:tt)*) => {
+        $crate::sql!(@clauses $crate::SecondaryPart::order_by($query, &vec![$($column.to_string()),*]); $($rest)*)
+    };
+    (@clauses $query:expr; group_by [$($column:expr),* $(,)?] $($rest:tt)*) => {
+        $crate::sql!(@clauses $crate::SecondaryPart::group_by($query, &vec![$($column.to_string()),*]); $($rest)*)
+    };
+    (@clauses $query:expr; returning [$($column:expr),* $(,)?] $($rest:tt)*) => {
+        $crate::sql!(@clauses $crate::SecondaryPart::returning($query, &vec![$($column.to_string()),*]); $($rest)*)
+    };
+    (@clauses $query:expr; limit $n:literal $($rest:tt)*) => {
+        $crate::sql!(@clauses $crate::SecondaryPart::limit($query, $n); $($rest)*)
+    };
+    (@clauses $query:expr; offset $n:literal $($rest:tt)*) => {
+        $crate::sql!(@clauses $crate::SecondaryPart::offset($query, $n); $($rest)*)
+    };
+}
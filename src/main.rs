@@ -1,6 +1,3 @@
-pub mod ast_builder;
-
-
 fn main() {
     println!("Hello, world!");
 }
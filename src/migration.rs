@@ -0,0 +1,122 @@
+use crate::ast_builder::*;
+use crate::executor::{ExecError, Executor};
+use crate::transaction::with_transaction;
+use std::collections::HashSet;
+
+#[derive(Clone, Debug)]
+pub struct Migration {
+    pub version: i64,
+    pub up: String,
+    pub down: String,
+}
+
+const MIGRATIONS_TABLE: &str = "schema_migrations";
+
+// Applies the migrations that are not yet recorded in `schema_migrations`,
+// in version order, each in its own transaction together with the row that
+// records it. A migration's `up` may hold several statements. Returns the
+// versions that were applied.
+pub fn run_migrations(
+    exec: &impl Executor,
+    migrations: &[Migration],
+) -> Result<Vec<i64>, ExecError> {
+    exec.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (version BIGINT PRIMARY KEY)",
+            MIGRATIONS_TABLE
+        ),
+        &[],
+    )?;
+    let model = Model {
+        name: MIGRATIONS_TABLE.to_string(),
+        fields: Some(vec!["version".to_string()]),
+        ..Default::default()
+    };
+    let applied: HashSet<i64> = exec
+        .query_statement(&select(&model))?
+        .iter()
+        .filter_map(|row| row.get("version")?.parse().ok())
+        .collect();
+
+    let mut pending: Vec<&Migration> = migrations
+        .iter()
+        .filter(|migration| !applied.contains(&migration.version))
+        .collect();
+    pending.sort_by_key(|migration| migration.version);
+
+    let mut versions = Vec::new();
+    for migration in pending {
+        with_transaction(exec, |transaction| {
+            transaction.execute_batch(&migration.up)?;
+            transaction.execute_statement(
+                &insert(&model).values_typed(&vec![Value::Int(migration.version)]),
+            )
+        })?;
+        versions.push(migration.version);
+    }
+    Ok(versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{MockExecutor, Row};
+
+    fn migrations() -> Vec<Migration> {
+        vec![
+            Migration {
+                version: 2,
+                up: "ALTER TABLE users ADD COLUMN age INTEGER".to_string(),
+                down: "ALTER TABLE users DROP COLUMN age".to_string(),
+            },
+            Migration {
+                version: 1,
+                up: "CREATE TABLE users (id INTEGER PRIMARY KEY)".to_string(),
+                down: "DROP TABLE users".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_run_migrations() {
+        let executor = MockExecutor::new();
+        assert_eq!(
+            run_migrations(&executor, &migrations()).unwrap(),
+            vec![1, 2]
+        );
+        let statements: Vec<String> = executor.log().into_iter().map(|(sql, _)| sql).collect();
+        assert_eq!(
+            statements,
+            vec![
+                "CREATE TABLE IF NOT EXISTS schema_migrations (version BIGINT PRIMARY KEY)",
+                "SELECT version FROM schema_migrations",
+                "BEGIN",
+                "CREATE TABLE users (id INTEGER PRIMARY KEY)",
+                "INSERT INTO schema_migrations (version) VALUES ($1)",
+                "COMMIT",
+                "BEGIN",
+                "ALTER TABLE users ADD COLUMN age INTEGER",
+                "INSERT INTO schema_migrations (version) VALUES ($1)",
+                "COMMIT",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_migrations_skips_applied() {
+        let applied = [1, 2]
+            .iter()
+            .map(|version| {
+                let mut row = Row::new();
+                row.insert("version".to_string(), version.to_string());
+                row
+            })
+            .collect();
+        let executor = MockExecutor::new().with_rows(applied);
+        assert_eq!(
+            run_migrations(&executor, &migrations()).unwrap(),
+            Vec::<i64>::new()
+        );
+        assert_eq!(executor.log().len(), 2);
+    }
+}
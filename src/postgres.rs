@@ -0,0 +1,299 @@
+use crate::ast_builder::{compile_parameterized, QueryBlock};
+use crate::executor::{ExecError, Row};
+use bytes::BytesMut;
+use std::error::Error;
+use tokio_postgres::types::{to_sql_checked, Format, FromSql, IsNull, Kind, ToSql, Type};
+use tokio_postgres::Client;
+
+// The builder keeps every parameter as a string, so parameters are sent in
+// the text format and the server parses them into the column's type.
+#[derive(Debug)]
+struct TextParam<'a>(&'a str);
+
+impl ToSql for TextParam<'_> {
+    fn to_sql(
+        &self,
+        _ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        out.extend_from_slice(self.0.as_bytes());
+        Ok(IsNull::No)
+    }
+
+    fn accepts(_ty: &Type) -> bool {
+        true
+    }
+
+    fn encode_format(&self, _ty: &Type) -> Format {
+        Format::Text
+    }
+
+    to_sql_checked!();
+}
+
+impl From<tokio_postgres::Error> for ExecError {
+    fn from(error: tokio_postgres::Error) -> Self {
+        ExecError::Backend(Box::new(error))
+    }
+}
+
+// Runs `QueryBlock`s on a tokio-postgres client. Unlike the `Executor`
+// trait its methods are async, as the client is.
+pub struct PostgresExecutor {
+    client: Client,
+}
+
+impl PostgresExecutor {
+    pub fn new(client: Client) -> Self {
+        PostgresExecutor { client }
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub async fn execute(&self, statement: &QueryBlock) -> Result<u64, ExecError> {
+        let (sql, params) = compile_parameterized(statement);
+        let params = text_params(&params);
+        Ok(self.client.execute(sql.as_str(), &as_dyn(&params)).await?)
+    }
+
+    pub async fn query(&self, statement: &QueryBlock) -> Result<Vec<Row>, ExecError> {
+        let (sql, params) = compile_parameterized(statement);
+        let params = text_params(&params);
+        let rows = self.client.query(sql.as_str(), &as_dyn(&params)).await?;
+        rows.iter().map(to_row).collect()
+    }
+
+    // Runs a script of several `;`-separated statements, such as a
+    // migration's `up`, without parameters.
+    pub async fn execute_batch(&self, sql: &str) -> Result<(), ExecError> {
+        Ok(self.client.batch_execute(sql).await?)
+    }
+}
+
+fn text_params(params: &[String]) -> Vec<TextParam<'_>> {
+    params.iter().map(|param| TextParam(param)).collect()
+}
+
+fn as_dyn<'a>(params: &'a [TextParam<'a>]) -> Vec<&'a (dyn ToSql + Sync)> {
+    params
+        .iter()
+        .map(|param| param as &(dyn ToSql + Sync))
+        .collect()
+}
+
+// NULL columns are left out of the row rather than given a placeholder.
+fn to_row(row: &tokio_postgres::Row) -> Result<Row, ExecError> {
+    let mut map = Row::new();
+    for (index, column) in row.columns().iter().enumerate() {
+        if let Some(TextValue(value)) = row.try_get::<_, Option<TextValue>>(index)? {
+            map.insert(column.name().to_string(), value);
+        }
+    }
+    Ok(map)
+}
+
+// A column value rendered the way Postgres prints it in the text format.
+// Results arrive in the binary format, so each supported type is decoded
+// here; a column of any other type is reported as a conversion error
+// instead of being guessed at. TIMESTAMPTZ is printed in UTC.
+struct TextValue(String);
+
+type DecodeError = Box<dyn Error + Sync + Send>;
+
+impl<'a> FromSql<'a> for TextValue {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, DecodeError> {
+        let value = match *ty {
+            Type::BOOL => if bool::from_sql(ty, raw)? { "t" } else { "f" }.to_string(),
+            Type::INT2 => i16::from_sql(ty, raw)?.to_string(),
+            Type::INT4 => i32::from_sql(ty, raw)?.to_string(),
+            Type::INT8 => i64::from_sql(ty, raw)?.to_string(),
+            Type::OID => u32::from_sql(ty, raw)?.to_string(),
+            Type::FLOAT4 => float(f32::from_sql(ty, raw)? as f64),
+            Type::FLOAT8 => float(f64::from_sql(ty, raw)?),
+            Type::NUMERIC => numeric(raw)?,
+            Type::DATE => match i32::from_sql(ty, raw)? {
+                i32::MAX => "infinity".to_string(),
+                i32::MIN => "-infinity".to_string(),
+                days => date(days as i64),
+            },
+            Type::TIME => time(i64::from_sql(ty, raw)?),
+            Type::TIMESTAMP => timestamp(i64::from_sql(&Type::INT8, raw)?, ""),
+            Type::TIMESTAMPTZ => timestamp(i64::from_sql(&Type::INT8, raw)?, "+00"),
+            Type::UUID => uuid(raw)?,
+            // The binary JSONB format is a version byte followed by the text.
+            Type::JSONB => match raw.split_first() {
+                Some((1, text)) => std::str::from_utf8(text)?.to_string(),
+                _ => return Err("unsupported JSONB version".into()),
+            },
+            _ => std::str::from_utf8(raw)?.to_string(),
+        };
+        Ok(TextValue(value))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        matches!(
+            *ty,
+            Type::BOOL
+                | Type::INT2
+                | Type::INT4
+                | Type::INT8
+                | Type::OID
+                | Type::FLOAT4
+                | Type::FLOAT8
+                | Type::NUMERIC
+                | Type::DATE
+                | Type::TIME
+                | Type::TIMESTAMP
+                | Type::TIMESTAMPTZ
+                | Type::UUID
+                | Type::JSON
+                | Type::JSONB
+                | Type::TEXT
+                | Type::VARCHAR
+                | Type::BPCHAR
+                | Type::NAME
+                | Type::UNKNOWN
+        ) || matches!(ty.kind(), Kind::Enum(_))
+    }
+}
+
+fn float(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+// See `numeric_send` in the Postgres sources: a digit count, the weight of
+// the first digit, a sign and the display scale, then base-10000 digits.
+fn numeric(raw: &[u8]) -> Result<String, DecodeError> {
+    let word = |index: usize| -> Result<u16, DecodeError> {
+        raw.get(index * 2..index * 2 + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+            .ok_or_else(|| "truncated NUMERIC value".into())
+    };
+    let count = word(0)? as usize;
+    let weight = word(1)? as i16 as i64;
+    let sign = word(2)?;
+    let scale = word(3)? as usize;
+    let digits = (0..count)
+        .map(|index| word(4 + index))
+        .collect::<Result<Vec<u16>, DecodeError>>()?;
+    match sign {
+        0x0000 | 0x4000 => {}
+        0xC000 => return Ok("NaN".to_string()),
+        0xD000 => return Ok("Infinity".to_string()),
+        0xF000 => return Ok("-Infinity".to_string()),
+        _ => return Err("invalid NUMERIC sign".into()),
+    }
+    // Digit `k` is worth 10000^(weight - k); positions outside the stored
+    // digits are zero.
+    let digit = |k: i64| -> u16 {
+        if k >= 0 && (k as usize) < digits.len() {
+            digits[k as usize]
+        } else {
+            0
+        }
+    };
+    let mut text = String::new();
+    if sign == 0x4000 {
+        text.push('-');
+    }
+    if weight < 0 {
+        text.push('0');
+    } else {
+        text.push_str(&digit(0).to_string());
+        for k in 1..=weight {
+            text.push_str(&format!("{:04}", digit(k)));
+        }
+    }
+    if scale > 0 {
+        let mut fraction = String::new();
+        let mut k = weight + 1;
+        while fraction.len() < scale {
+            fraction.push_str(&format!("{:04}", digit(k)));
+            k += 1;
+        }
+        fraction.truncate(scale);
+        text.push('.');
+        text.push_str(&fraction);
+    }
+    Ok(text)
+}
+
+const MICROS_PER_DAY: i64 = 86_400_000_000;
+
+// Dates count days from 2000-01-01; the conversion to a civil date follows
+// Howard Hinnant's `civil_from_days`.
+fn date(days: i64) -> String {
+    let z = days + 10_957 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    if year <= 0 {
+        format!("{:04}-{:02}-{:02} BC", 1 - year, month, day)
+    } else {
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+// Fractional seconds are printed only when present, without trailing zeros.
+fn time(micros: i64) -> String {
+    let seconds = micros / 1_000_000;
+    let mut text = format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    let fraction = micros % 1_000_000;
+    if fraction != 0 {
+        text.push_str(format!(".{:06}", fraction).trim_end_matches('0'));
+    }
+    text
+}
+
+fn timestamp(micros: i64, zone: &str) -> String {
+    match micros {
+        i64::MAX => "infinity".to_string(),
+        i64::MIN => "-infinity".to_string(),
+        _ => {
+            let day = date(micros.div_euclid(MICROS_PER_DAY));
+            let time = time(micros.rem_euclid(MICROS_PER_DAY));
+            match day.strip_suffix(" BC") {
+                Some(day) => format!("{} {}{} BC", day, time, zone),
+                None => format!("{} {}{}", day, time, zone),
+            }
+        }
+    }
+}
+
+fn uuid(raw: &[u8]) -> Result<String, DecodeError> {
+    if raw.len() != 16 {
+        return Err("invalid UUID length".into());
+    }
+    let hex: String = raw.iter().map(|byte| format!("{:02x}", byte)).collect();
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    ))
+}
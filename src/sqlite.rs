@@ -0,0 +1,186 @@
+use crate::executor::{ExecError, Executor, Row};
+use rusqlite::types::ValueRef;
+use rusqlite::{params_from_iter, Connection};
+
+impl From<rusqlite::Error> for ExecError {
+    fn from(error: rusqlite::Error) -> Self {
+        ExecError::Backend(Box::new(error))
+    }
+}
+
+// Parameters are bound as text and left to SQLite's column affinity to
+// convert. The `$n` placeholders of `compile_parameterized` are numbered in
+// order of appearance, so binding them by position lines up.
+pub struct SqliteExecutor {
+    connection: Connection,
+}
+
+impl SqliteExecutor {
+    pub fn new(connection: Connection) -> Self {
+        SqliteExecutor { connection }
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+impl Executor for SqliteExecutor {
+    fn execute(&self, sql: &str, params: &[String]) -> Result<u64, ExecError> {
+        let affected = self.connection.execute(sql, params_from_iter(params))?;
+        Ok(affected as u64)
+    }
+
+    fn query(&self, sql: &str, params: &[String]) -> Result<Vec<Row>, ExecError> {
+        let mut statement = self.connection.prepare(sql)?;
+        let names: Vec<String> = statement
+            .column_names()
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        let mut rows = statement.query(params_from_iter(params))?;
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            let mut map = Row::new();
+            for (index, name) in names.iter().enumerate() {
+                if let Some(value) = to_string(row.get_ref(index)?) {
+                    map.insert(name.clone(), value);
+                }
+            }
+            result.push(map);
+        }
+        Ok(result)
+    }
+
+    fn execute_batch(&self, sql: &str) -> Result<(), ExecError> {
+        Ok(self.connection.execute_batch(sql)?)
+    }
+}
+
+// NULL columns are left out of the row rather than given a placeholder.
+fn to_string(value: ValueRef<'_>) -> Option<String> {
+    match value {
+        ValueRef::Null => None,
+        ValueRef::Integer(value) => Some(value.to_string()),
+        ValueRef::Real(value) => Some(value.to_string()),
+        ValueRef::Text(value) | ValueRef::Blob(value) => {
+            Some(String::from_utf8_lossy(value).into_owned())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_builder::*;
+    use crate::ddl::*;
+    use crate::migration::*;
+
+    #[test]
+    fn test_sqlite_round_trip() {
+        let executor = SqliteExecutor::new(Connection::open_in_memory().unwrap());
+        let id = ColumnDef {
+            primary_key: true,
+            ..ColumnDef::new("id", "INTEGER")
+        };
+        let name = ColumnDef {
+            nullable: false,
+            ..ColumnDef::new("name", "TEXT")
+        };
+        let nickname = ColumnDef::new("nickname", "TEXT");
+        executor
+            .execute(
+                &create_table(
+                    &Model::builder().name("users").build(),
+                    &vec![id, name, nickname],
+                    &vec![],
+                )
+                .unwrap(),
+                &[],
+            )
+            .unwrap();
+
+        let model = Model {
+            name: "users".to_string(),
+            fields: Some(vec!["id".to_string(), "name".to_string()]),
+            ..Default::default()
+        };
+        let inserted = executor
+            .execute_statement(&insert(&model).values_many(&vec![
+                vec!["1".to_string(), "alice".to_string()],
+                vec!["2".to_string(), "bob".to_string()],
+            ]))
+            .unwrap();
+        assert_eq!(inserted, 2);
+
+        let all = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let rows = executor
+            .query_statement(&select(&all).where_clause(&"id = 2".to_string()))
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["id"], "2");
+        assert_eq!(rows[0]["name"], "bob");
+        assert!(!rows[0].contains_key("nickname"));
+    }
+
+    #[test]
+    fn test_sqlite_boolean_parameters() {
+        let executor = SqliteExecutor::new(Connection::open_in_memory().unwrap());
+        executor
+            .execute("CREATE TABLE flags (id INTEGER, active BOOLEAN)", &[])
+            .unwrap();
+        let model = Model {
+            name: "flags".to_string(),
+            fields: Some(vec!["id".to_string(), "active".to_string()]),
+            ..Default::default()
+        };
+        executor
+            .execute_statement(
+                &insert(&model).values_typed(&vec![Value::Int(1), Value::Bool(true)]),
+            )
+            .unwrap();
+        let rows = executor
+            .query_statement(&select(&model).where_eq(&"active".to_string(), &Value::Bool(true)))
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["active"], "1");
+    }
+
+    #[test]
+    fn test_sqlite_migrations() {
+        let executor = SqliteExecutor::new(Connection::open_in_memory().unwrap());
+        let migrations = vec![Migration {
+            version: 1,
+            up: "CREATE TABLE users (id INTEGER PRIMARY KEY)".to_string(),
+            down: "DROP TABLE users".to_string(),
+        }];
+        assert_eq!(run_migrations(&executor, &migrations).unwrap(), vec![1]);
+        assert_eq!(
+            run_migrations(&executor, &migrations).unwrap(),
+            Vec::<i64>::new()
+        );
+    }
+
+    #[test]
+    fn test_sqlite_multi_statement_migration() {
+        let executor = SqliteExecutor::new(Connection::open_in_memory().unwrap());
+        let migrations = vec![Migration {
+            version: 1,
+            up: "CREATE TABLE a (id INTEGER); CREATE INDEX ia ON a (id)".to_string(),
+            down: "DROP TABLE a".to_string(),
+        }];
+        assert_eq!(run_migrations(&executor, &migrations).unwrap(), vec![1]);
+        let indexes = executor
+            .query(
+                "SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = 'a'",
+                &[],
+            )
+            .unwrap();
+        assert_eq!(indexes.len(), 1);
+        assert_eq!(indexes[0]["name"], "ia");
+    }
+}
@@ -0,0 +1,202 @@
+use crate::executor::{ExecError, Executor, Row};
+
+// A transaction on top of any `Executor`. It executes statements like the
+// executor it wraps, and one that is dropped without being committed is
+// rolled back.
+pub struct Transaction<'a, E: Executor> {
+    executor: &'a E,
+    finished: bool,
+}
+
+impl<'a, E: Executor> Transaction<'a, E> {
+    pub fn begin(executor: &'a E) -> Result<Self, ExecError> {
+        executor.execute("BEGIN", &[])?;
+        Ok(Transaction {
+            executor,
+            finished: false,
+        })
+    }
+
+    pub fn commit(mut self) -> Result<(), ExecError> {
+        self.finished = true;
+        self.executor.execute("COMMIT", &[])?;
+        Ok(())
+    }
+
+    pub fn rollback(mut self) -> Result<(), ExecError> {
+        self.finished = true;
+        self.executor.execute("ROLLBACK", &[])?;
+        Ok(())
+    }
+
+    pub fn savepoint(&self, name: &str) -> Result<(), ExecError> {
+        self.savepoint_command("SAVEPOINT", name)
+    }
+
+    pub fn rollback_to(&self, name: &str) -> Result<(), ExecError> {
+        self.savepoint_command("ROLLBACK TO SAVEPOINT", name)
+    }
+
+    pub fn release(&self, name: &str) -> Result<(), ExecError> {
+        self.savepoint_command("RELEASE SAVEPOINT", name)
+    }
+
+    // Savepoint names cannot be bound as parameters, so they are restricted
+    // to plain identifiers before being interpolated.
+    fn savepoint_command(&self, command: &str, name: &str) -> Result<(), ExecError> {
+        let mut chars = name.chars();
+        let valid = chars
+            .next()
+            .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(ExecError::InvalidSavepoint {
+                name: name.to_string(),
+            });
+        }
+        self.executor
+            .execute(&format!("{} {}", command, name), &[])?;
+        Ok(())
+    }
+}
+
+impl<E: Executor> Executor for Transaction<'_, E> {
+    fn execute(&self, sql: &str, params: &[String]) -> Result<u64, ExecError> {
+        self.executor.execute(sql, params)
+    }
+
+    fn query(&self, sql: &str, params: &[String]) -> Result<Vec<Row>, ExecError> {
+        self.executor.query(sql, params)
+    }
+
+    fn execute_batch(&self, sql: &str) -> Result<(), ExecError> {
+        self.executor.execute_batch(sql)
+    }
+}
+
+impl<E: Executor> Drop for Transaction<'_, E> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.executor.execute("ROLLBACK", &[]);
+        }
+    }
+}
+
+// Commits when `body` succeeds and rolls back when it returns an error,
+// which is then passed on to the caller.
+pub fn with_transaction<E, T, Err, F>(executor: &E, body: F) -> Result<T, Err>
+where
+    E: Executor,
+    Err: From<ExecError>,
+    F: FnOnce(&Transaction<'_, E>) -> Result<T, Err>,
+{
+    let transaction = Transaction::begin(executor)?;
+    match body(&transaction) {
+        Ok(value) => {
+            transaction.commit()?;
+            Ok(value)
+        }
+        Err(error) => {
+            transaction.rollback()?;
+            Err(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_builder::*;
+    use crate::executor::MockExecutor;
+
+    fn statements(executor: &MockExecutor) -> Vec<String> {
+        executor.log().into_iter().map(|(sql, _)| sql).collect()
+    }
+
+    #[test]
+    fn test_with_transaction_commits() {
+        let executor = MockExecutor::new().with_affected(1);
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let affected = with_transaction(&executor, |transaction| {
+            transaction
+                .execute_statement(&delete(&model).where_eq(&"id".to_string(), &Value::Int(1)))
+        })
+        .unwrap();
+        assert_eq!(affected, 1);
+        assert_eq!(
+            statements(&executor),
+            vec!["BEGIN", "DELETE FROM users WHERE id = $1", "COMMIT"]
+        );
+    }
+
+    #[test]
+    fn test_with_transaction_rolls_back_on_error() {
+        let executor = MockExecutor::new();
+        let model = Model {
+            name: "users".to_string(),
+            fields: None,
+            ..Default::default()
+        };
+        let result: Result<(), ExecError> = with_transaction(&executor, |transaction| {
+            transaction
+                .execute_statement(&delete(&model).where_eq(&"id".to_string(), &Value::Int(1)))?;
+            Err(ExecError::Backend("constraint violated".into()))
+        });
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "database error: constraint violated"
+        );
+        assert_eq!(
+            statements(&executor),
+            vec!["BEGIN", "DELETE FROM users WHERE id = $1", "ROLLBACK"]
+        );
+    }
+
+    #[test]
+    fn test_dropped_transaction_rolls_back() {
+        let executor = MockExecutor::new();
+        {
+            let _transaction = Transaction::begin(&executor).unwrap();
+        }
+        assert_eq!(statements(&executor), vec!["BEGIN", "ROLLBACK"]);
+    }
+
+    #[test]
+    fn test_savepoint_rollback() {
+        let executor = MockExecutor::new();
+        let transaction = Transaction::begin(&executor).unwrap();
+        transaction.savepoint("before_import").unwrap();
+        transaction.execute("DELETE FROM users", &[]).unwrap();
+        transaction.rollback_to("before_import").unwrap();
+        transaction.release("before_import").unwrap();
+        transaction.commit().unwrap();
+        assert_eq!(
+            statements(&executor),
+            vec![
+                "BEGIN",
+                "SAVEPOINT before_import",
+                "DELETE FROM users",
+                "ROLLBACK TO SAVEPOINT before_import",
+                "RELEASE SAVEPOINT before_import",
+                "COMMIT",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_savepoint_rejects_invalid_name() {
+        let executor = MockExecutor::new();
+        let transaction = Transaction::begin(&executor).unwrap();
+        let error = transaction.savepoint("x; DROP TABLE users").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`x; DROP TABLE users` is not a valid savepoint name"
+        );
+        drop(transaction);
+        assert_eq!(statements(&executor), vec!["BEGIN", "ROLLBACK"]);
+    }
+}
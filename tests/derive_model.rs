@@ -0,0 +1,36 @@
+use my_orm::{compile_statement, select, Model};
+
+#[allow(dead_code)]
+#[derive(Model)]
+struct UserAccount {
+    id: i64,
+    display_name: String,
+}
+
+#[allow(dead_code)]
+#[derive(Model)]
+#[model(table = "people")]
+struct HTTPPerson {
+    id: i64,
+}
+
+#[test]
+fn test_derive_model() {
+    let model = UserAccount::as_model();
+    assert_eq!(model.name, "user_account");
+    assert_eq!(
+        model.fields,
+        Some(vec!["id".to_string(), "display_name".to_string()])
+    );
+    assert_eq!(
+        compile_statement(&select(&model)),
+        "SELECT id, display_name FROM user_account"
+    );
+}
+
+#[test]
+fn test_derive_model_table_override() {
+    let model = HTTPPerson::as_model();
+    assert_eq!(model.name, "people");
+    assert_eq!(model.fields, Some(vec!["id".to_string()]));
+}
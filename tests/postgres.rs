@@ -0,0 +1,158 @@
+#![cfg(feature = "postgres")]
+
+use my_orm::*;
+use tokio_postgres::NoTls;
+
+// Runs against the database named by MY_ORM_POSTGRES_URL, for example
+// `host=localhost user=postgres`, and is skipped when it is not set.
+async fn connect() -> Option<PostgresExecutor> {
+    let url = std::env::var("MY_ORM_POSTGRES_URL").ok()?;
+    let (client, connection) = tokio_postgres::connect(&url, NoTls).await.unwrap();
+    tokio::spawn(connection);
+    Some(PostgresExecutor::new(client))
+}
+
+#[tokio::test]
+async fn test_postgres_round_trip() {
+    let Some(executor) = connect().await else {
+        return;
+    };
+    executor
+        .client()
+        .batch_execute(concat!(
+            "DROP TABLE IF EXISTS my_orm_users;",
+            "CREATE TABLE my_orm_users (id INTEGER PRIMARY KEY, name TEXT NOT NULL)"
+        ))
+        .await
+        .unwrap();
+
+    let model = Model {
+        name: "my_orm_users".to_string(),
+        fields: Some(vec!["id".to_string(), "name".to_string()]),
+        ..Default::default()
+    };
+    let inserted = executor
+        .execute(&insert(&model).values_many(&vec![
+            vec!["1".to_string(), "alice".to_string()],
+            vec!["2".to_string(), "bob".to_string()],
+        ]))
+        .await
+        .unwrap();
+    assert_eq!(inserted, 2);
+
+    let rows = executor
+        .query(&select(&model).where_clause(&"id = 2".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["id"], "2");
+    assert_eq!(rows[0]["name"], "bob");
+
+    executor
+        .client()
+        .batch_execute("DROP TABLE my_orm_users")
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_postgres_text_values() {
+    let Some(executor) = connect().await else {
+        return;
+    };
+    executor
+        .client()
+        .batch_execute(concat!(
+            "DROP TABLE IF EXISTS my_orm_events;",
+            "CREATE TABLE my_orm_events (id INTEGER PRIMARY KEY, price NUMERIC(10, 2), ",
+            "day DATE, at TIMESTAMP, token UUID, payload JSONB, active BOOLEAN)"
+        ))
+        .await
+        .unwrap();
+
+    let model = Model {
+        name: "my_orm_events".to_string(),
+        fields: Some(
+            ["id", "price", "day", "at", "token", "payload", "active"]
+                .iter()
+                .map(|field| field.to_string())
+                .collect(),
+        ),
+        ..Default::default()
+    };
+    executor
+        .execute(&insert(&model).values_typed(&vec![
+            Value::Int(1),
+            Value::Text("12.50".to_string()),
+            Value::Text("2024-01-01".to_string()),
+            Value::Text("2024-01-01 10:30:00".to_string()),
+            Value::Text("a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11".to_string()),
+            Value::Text("{\"a\": 1}".to_string()),
+            Value::Bool(true),
+        ]))
+        .await
+        .unwrap();
+
+    let rows = executor
+        .query(&select(&model).where_eq(&"id".to_string(), &Value::Int(1)))
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["price"], "12.50");
+    assert_eq!(rows[0]["day"], "2024-01-01");
+    assert_eq!(rows[0]["at"], "2024-01-01 10:30:00");
+    assert_eq!(rows[0]["token"], "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11");
+    assert_eq!(rows[0]["payload"], "{\"a\": 1}");
+    assert_eq!(rows[0]["active"], "t");
+
+    let averages = Model {
+        name: "my_orm_events".to_string(),
+        fields: Some(vec![alias(&avg("price"), "average")]),
+        ..Default::default()
+    };
+    let rows = executor.query(&select(&averages)).await.unwrap();
+    assert_eq!(rows[0]["average"], "12.5000000000000000");
+
+    executor
+        .client()
+        .batch_execute(concat!(
+            "DROP TABLE IF EXISTS my_orm_samples;",
+            "CREATE TABLE my_orm_samples (id INTEGER, amount NUMERIC, ratio DOUBLE PRECISION, ",
+            "at TIMESTAMPTZ, span INTERVAL);",
+            "SET TIME ZONE 'UTC';",
+            "INSERT INTO my_orm_samples VALUES (1, -1234567.000089, 0.25, ",
+            "'1999-12-31 23:59:59.5+00', '1 day')"
+        ))
+        .await
+        .unwrap();
+    let samples = Model {
+        name: "my_orm_samples".to_string(),
+        fields: Some(vec![
+            "amount".to_string(),
+            "ratio".to_string(),
+            "at".to_string(),
+        ]),
+        ..Default::default()
+    };
+    let rows = executor
+        .query(&select(&samples).where_eq(&"id".to_string(), &Value::Int(1)))
+        .await
+        .unwrap();
+    assert_eq!(rows[0]["amount"], "-1234567.000089");
+    assert_eq!(rows[0]["ratio"], "0.25");
+    assert_eq!(rows[0]["at"], "1999-12-31 23:59:59.5+00");
+
+    // A type without a text rendering is an error, not a guessed value.
+    let spans = Model {
+        name: "my_orm_samples".to_string(),
+        fields: Some(vec!["span".to_string()]),
+        ..Default::default()
+    };
+    assert!(executor.query(&select(&spans)).await.is_err());
+
+    executor
+        .client()
+        .batch_execute("DROP TABLE my_orm_events; DROP TABLE my_orm_samples")
+        .await
+        .unwrap();
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use my_orm::{
+    compile_parameterized, compile_statement, delete, insert, select, update, Model, SecondaryPart,
+    StatementType, Value,
+};
+
+#[test]
+fn test_select_through_public_api() {
+    let model = Model {
+        name: "users".to_string(),
+        fields: Some(vec!["id".to_string(), "name".to_string()]),
+        ..Default::default()
+    };
+    let query = select(&model)
+        .where_clause(&"id = 1".to_string())
+        .order_by(&vec!["name".to_string()]);
+    assert_eq!(
+        compile_statement(&query),
+        "SELECT id, name FROM users WHERE id = 1 ORDER BY name"
+    );
+    assert_eq!(query.query_part(), "SELECT id, name FROM users");
+    assert!(matches!(query.statement_type(), StatementType::Select));
+    assert_eq!(query.secondary_part().unwrap().query_part(), "WHERE id = 1");
+}
+
+#[test]
+fn test_write_statements_through_public_api() {
+    let model = Model {
+        name: "users".to_string(),
+        fields: Some(vec!["name".to_string()]),
+        ..Default::default()
+    };
+    let query = insert(&model).values(&vec!["John".to_string()]);
+    assert_eq!(
+        compile_statement(&query),
+        "INSERT INTO users (name) VALUES ('John')"
+    );
+
+    let mut arguments = HashMap::new();
+    arguments.insert("name".to_string(), "Jane".to_string());
+    let query = update(&model)
+        .unwrap()
+        .set(&arguments)
+        .where_eq(&"id".to_string(), &Value::Int(2));
+    assert_eq!(
+        compile_parameterized(&query),
+        (
+            "UPDATE users SET name = $1 WHERE id = $2".to_string(),
+            vec!["Jane".to_string(), "2".to_string()]
+        )
+    );
+
+    let query = delete(&model).where_clause(&"id = 3".to_string());
+    assert_eq!(compile_statement(&query), "DELETE FROM users WHERE id = 3");
+}
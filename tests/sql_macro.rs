@@ -0,0 +1,39 @@
+use my_orm::*;
+
+#[test]
+fn test_sql_select() {
+    let query = sql!(select users where "id = 1" order_by ["name"]);
+    assert_eq!(
+        compile_statement(&query),
+        "SELECT * FROM users WHERE id = 1 ORDER BY name"
+    );
+}
+
+#[test]
+fn test_sql_select_fields() {
+    let query = sql!(select users ["id", "name"] where "age > 18" where "active = 1" limit 10);
+    assert_eq!(
+        compile_statement(&query),
+        "SELECT id, name FROM users WHERE age > 18 AND active = 1 LIMIT 10"
+    );
+}
+
+#[test]
+fn test_sql_update() {
+    let query = sql!(update users set { "name" => "bob" } where "id = 1");
+    assert_eq!(
+        compile_statement(&query),
+        "UPDATE users SET name = 'bob' WHERE id = 1"
+    );
+}
+
+#[test]
+fn test_sql_insert_and_delete() {
+    let query = sql!(insert users ["name"] values ["bob"] returning ["id"]);
+    assert_eq!(
+        compile_statement(&query),
+        "INSERT INTO users (name) VALUES ('bob') RETURNING id"
+    );
+    let query = sql!(delete users where "id = 1");
+    assert_eq!(compile_statement(&query), "DELETE FROM users WHERE id = 1");
+}